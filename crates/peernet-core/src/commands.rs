@@ -1,7 +1,14 @@
 pub use libp2p::PeerId;
 
-use crate::{DhtKey, DhtValue, GossipPayload, TopicName};
+use crate::{
+    CommandError, ConnectionDirection, DhtKey, DhtLookup, DhtMode, DhtQuorum, DhtValue,
+    GossipPayload, LatencyStats, PeerInfo, Reachability, TopicName,
+};
 use libp2p::Multiaddr;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+pub type Reply<T> = oneshot::Sender<Result<T, CommandError>>;
 
 #[derive(Debug)]
 pub enum NetworkCommand {
@@ -12,25 +19,75 @@ pub enum NetworkCommand {
     Publish {
         topic: TopicName,
         payload: GossipPayload,
+        reply: Reply<()>,
     },
     Subscribe {
         topic: TopicName,
+        reply: Reply<()>,
     },
     Unsubscribe {
         topic: TopicName,
+        reply: Reply<()>,
+    },
+    ListSubscriptions {
+        reply: Reply<Vec<TopicName>>,
+    },
+    ListPeers {
+        reply: Reply<Vec<PeerId>>,
+    },
+    ListPeerInfo {
+        reply: Reply<Vec<PeerInfo>>,
+    },
+    GetLatency {
+        peer_id: PeerId,
+        reply: Reply<Option<LatencyStats>>,
+    },
+    BlockPeer {
+        peer_id: PeerId,
+        reply: Reply<()>,
+    },
+    UnblockPeer {
+        peer_id: PeerId,
+        reply: Reply<()>,
+    },
+    AllowPeer {
+        peer_id: PeerId,
+        reply: Reply<()>,
+    },
+    DisallowPeer {
+        peer_id: PeerId,
+        reply: Reply<()>,
+    },
+    ListBlockedPeers {
+        reply: Reply<Vec<PeerId>>,
     },
     PutRecord {
         key: DhtKey,
         value: DhtValue,
+        ttl: Option<Duration>,
+        quorum: DhtQuorum,
+        reply: Reply<()>,
     },
     GetRecord {
         key: DhtKey,
+        quorum: DhtQuorum,
+        reply: Reply<DhtLookup>,
     },
     StartProviding {
         key: DhtKey,
+        reply: Reply<()>,
     },
     GetProviders {
         key: DhtKey,
+        reply: Reply<Vec<PeerId>>,
+    },
+    FindPeer {
+        peer_id: PeerId,
+        reply: Reply<Vec<Multiaddr>>,
+    },
+    GetClosestPeers {
+        key: DhtKey,
+        reply: Reply<Vec<PeerId>>,
     },
 }
 
@@ -38,7 +95,7 @@ pub enum NetworkCommand {
 pub enum NetworkEvent {
     Started {
         local_peer_id: PeerId,
-        listening_on: Multiaddr,
+        listening_on: Vec<Multiaddr>,
     },
     ShutdownComplete,
     PeerDiscovered {
@@ -50,6 +107,55 @@ pub enum NetworkEvent {
     PeerDisconnected {
         peer_id: PeerId,
     },
+    PeerIdentified {
+        peer_id: PeerId,
+        agent_version: String,
+        protocol_version: String,
+        protocols: Vec<String>,
+        listen_addrs: Vec<Multiaddr>,
+        observed_addr: Multiaddr,
+    },
+    PeerLatency {
+        peer_id: PeerId,
+        stats: LatencyStats,
+    },
+    PeerBlocked {
+        peer_id: PeerId,
+    },
+    PeerUnblocked {
+        peer_id: PeerId,
+    },
+    PeerAllowed {
+        peer_id: PeerId,
+    },
+    PeerDisallowed {
+        peer_id: PeerId,
+    },
+    RelayReservationAccepted {
+        relay_peer_id: PeerId,
+        renewal: bool,
+    },
+    RelayReservationGranted {
+        peer_id: PeerId,
+    },
+    RelayCircuitOpened {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+    },
+    RelayedConnection {
+        peer_id: PeerId,
+        relay_peer_id: PeerId,
+    },
+    ReachabilityChanged {
+        reachability: Reachability,
+        dht_mode: DhtMode,
+    },
+    ConnectionLimitReached {
+        peer_id: Option<PeerId>,
+        direction: ConnectionDirection,
+        limit: u32,
+        reason: String,
+    },
     Listening {
         address: Multiaddr,
     },
@@ -86,6 +192,12 @@ pub enum NetworkEvent {
     RecordNotFound {
         key: DhtKey,
     },
+    RecordExpired {
+        key: DhtKey,
+    },
+    RecordPersistFailed {
+        reason: String,
+    },
     ProviderRecordStored {
         key: DhtKey,
     },
@@ -93,9 +205,21 @@ pub enum NetworkEvent {
         key: DhtKey,
         providers: Vec<PeerId>,
     },
+    PeerFound {
+        peer_id: PeerId,
+        addresses: Vec<Multiaddr>,
+    },
+    ClosestPeers {
+        key: DhtKey,
+        peers: Vec<PeerId>,
+    },
     RoutingUpdated {
         peer_id: PeerId,
     },
+    BootstrapCompleted,
+    BootstrapFailed {
+        reason: String,
+    },
     CommandFailed {
         reason: String,
     },
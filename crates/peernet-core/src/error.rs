@@ -21,9 +21,24 @@ pub enum PeernetError {
 
     #[error("transport error: {reason}")]
     Transport { reason: String },
+
+    #[error("identity error at {path}: {reason}")]
+    Identity { path: String, reason: String },
+
+    #[error("storage error: {reason}")]
+    Storage { reason: String },
+
+    #[error("control socket error: {reason}")]
+    Control { reason: String },
+
+    #[error("config error: {reason}")]
+    Config { reason: String },
+
+    #[error("command failed: {0}")]
+    Command(#[from] CommandError),
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum CommandError {
     #[error("publish failed on topic {topic}: {reason}")]
     PublishFailed { topic: String, reason: String },
@@ -39,4 +54,20 @@ pub enum CommandError {
 
     #[error("dht operation failed for key {key}: {reason}")]
     DhtFailed { key: String, reason: String },
+
+    #[error("record not found for key {key}")]
+    RecordNotFound { key: String },
+
+    #[error("peer {peer} not found in the dht")]
+    PeerNotFound { peer: String },
+
+    #[error("peer list update failed for {peer}: {reason}")]
+    PeerListFailed { peer: String, reason: String },
+
+    #[error("quorum not reached for key {key}: {found} of {required} peers agreed")]
+    QuorumFailed {
+        key: String,
+        found: usize,
+        required: usize,
+    },
 }
@@ -2,7 +2,10 @@ mod commands;
 mod error;
 mod types;
 
-pub use commands::{InputCommand, NetworkCommand, NetworkEvent, PeerId};
+pub use commands::{InputCommand, NetworkCommand, NetworkEvent, PeerId, Reply};
 pub use error::{CommandError, PeernetError, PeernetResult};
 pub use libp2p::Multiaddr;
-pub use types::{DhtKey, DhtValue, GossipPayload, TopicName};
+pub use types::{
+    ConnectionDirection, DhtKey, DhtLookup, DhtMode, DhtQuorum, DhtValue, GossipPayload,
+    LatencyStats, PeerInfo, Reachability, TopicName,
+};
@@ -1,6 +1,11 @@
-use crate::PeernetError;
+use crate::{PeerId, PeernetError};
 use derive_more::{AsRef, Deref, Display};
-use std::fmt;
+use libp2p::Multiaddr;
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    time::{Duration, SystemTime},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display, AsRef, Deref)]
 pub struct TopicName(String);
@@ -123,6 +128,141 @@ impl AsRef<[u8]> for DhtValue {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DhtQuorum {
+    #[default]
+    One,
+    Majority,
+    All,
+    N(NonZeroUsize),
+}
+
+impl DhtQuorum {
+    pub fn required(&self, replication: usize) -> usize {
+        match self {
+            Self::One => 1,
+            Self::Majority => replication / 2 + 1,
+            Self::All => replication.max(1),
+            Self::N(n) => n.get(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhtLookup {
+    pub value: DhtValue,
+    pub agreeing: usize,
+    pub responses: usize,
+}
+
+impl DhtLookup {
+    pub fn from_values(values: Vec<DhtValue>) -> Option<Self> {
+        let responses = values.len();
+        let mut tally: Vec<(DhtValue, usize)> = Vec::new();
+        for value in values {
+            match tally.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, count)) => *count += 1,
+                None => tally.push((value, 1)),
+            }
+        }
+
+        let mut best: Option<(DhtValue, usize)> = None;
+        for (value, count) in tally {
+            if best.as_ref().is_none_or(|(_, c)| count > *c) {
+                best = Some((value, count));
+            }
+        }
+
+        best.map(|(value, agreeing)| Self {
+            value,
+            agreeing,
+            responses,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub last: Duration,
+    pub average: Duration,
+    pub min: Duration,
+    pub samples: u32,
+}
+
+impl LatencyStats {
+    pub fn new(rtt: Duration) -> Self {
+        Self {
+            last: rtt,
+            average: rtt,
+            min: rtt,
+            samples: 1,
+        }
+    }
+
+    pub fn record(&mut self, rtt: Duration) {
+        let samples = self.samples.saturating_add(1);
+        self.average = (self.average * self.samples + rtt) / samples;
+        self.samples = samples;
+        self.last = rtt;
+        self.min = self.min.min(rtt);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ConnectionDirection {
+    #[display("dialer")]
+    Dialer,
+    #[display("listener")]
+    Listener,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum Reachability {
+    #[display("unknown")]
+    Unknown,
+    #[display("public at {_0}")]
+    Public(Multiaddr),
+    #[display("private")]
+    Private,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum DhtMode {
+    #[display("client")]
+    Client,
+    #[display("server")]
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub peer_id: PeerId,
+    pub addresses: Vec<Multiaddr>,
+    pub direction: ConnectionDirection,
+    pub connections: usize,
+    pub connected_since: SystemTime,
+    pub agent_version: Option<String>,
+    pub protocols: Vec<String>,
+    pub topics: Vec<TopicName>,
+    pub latency: Option<LatencyStats>,
+}
+
+impl PeerInfo {
+    pub fn new(peer_id: PeerId, address: Multiaddr, direction: ConnectionDirection) -> Self {
+        Self {
+            peer_id,
+            addresses: vec![address],
+            direction,
+            connections: 1,
+            connected_since: SystemTime::now(),
+            agent_version: None,
+            protocols: Vec::new(),
+            topics: Vec::new(),
+            latency: None,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct GossipPayload(Vec<u8>);
 
@@ -212,4 +352,34 @@ mod tests {
     fn topic_name_validates_empty() {
         assert!(TopicName::new("").is_err());
     }
+
+    #[test]
+    fn dht_quorum_resolves_against_replication() {
+        assert_eq!(DhtQuorum::One.required(20), 1);
+        assert_eq!(DhtQuorum::Majority.required(20), 11);
+        assert_eq!(DhtQuorum::All.required(20), 20);
+        assert_eq!(DhtQuorum::N(NonZeroUsize::new(3).unwrap()).required(20), 3);
+    }
+
+    #[test]
+    fn latency_stats_track_last_average_and_min() {
+        let mut stats = LatencyStats::new(Duration::from_millis(30));
+        stats.record(Duration::from_millis(10));
+        stats.record(Duration::from_millis(20));
+        assert_eq!(stats.last, Duration::from_millis(20));
+        assert_eq!(stats.average, Duration::from_millis(20));
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.samples, 3);
+    }
+
+    #[test]
+    fn dht_lookup_picks_most_common_value() {
+        let a = DhtValue::new(b"a".to_vec()).unwrap();
+        let b = DhtValue::new(b"b".to_vec()).unwrap();
+        let lookup = DhtLookup::from_values(vec![b.clone(), a.clone(), a.clone()]).unwrap();
+        assert_eq!(lookup.value, a);
+        assert_eq!(lookup.agreeing, 2);
+        assert_eq!(lookup.responses, 3);
+        assert!(DhtLookup::from_values(Vec::new()).is_none());
+    }
 }
@@ -1,19 +1,54 @@
+use crate::store::PeernetStore;
+use libp2p::allow_block_list::{self, AllowedPeers, BlockedPeers};
+use libp2p::autonat;
+use libp2p::connection_limits;
 use libp2p::gossipsub;
+use libp2p::identify;
 use libp2p::kad;
 use libp2p::mdns;
-use libp2p::swarm::NetworkBehaviour;
+use libp2p::ping;
+use libp2p::relay;
+use libp2p::swarm::{NetworkBehaviour, behaviour::toggle::Toggle};
+use std::convert::Infallible;
 
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "PeernetBehaviourEvent")]
 pub struct PeernetBehaviour {
-    pub kademlia: kad::Behaviour<kad::store::MemoryStore>,
+    pub limits: connection_limits::Behaviour,
+    pub blocked: allow_block_list::Behaviour<BlockedPeers>,
+    pub allowed: Toggle<allow_block_list::Behaviour<AllowedPeers>>,
+    pub kademlia: kad::Behaviour<PeernetStore>,
     pub gossipsub: gossipsub::Behaviour,
-    pub mdns: mdns::tokio::Behaviour,
+    pub identify: identify::Behaviour,
+    pub ping: ping::Behaviour,
+    pub autonat: autonat::Behaviour,
+    pub relay_client: relay::client::Behaviour,
+    pub relay_server: Toggle<relay::Behaviour>,
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
 }
 
 #[derive(Debug, derive_more::From)]
 pub enum PeernetBehaviourEvent {
     Kademlia(kad::Event),
     Gossipsub(gossipsub::Event),
+    Identify(Box<identify::Event>),
+    Ping(ping::Event),
+    Autonat(autonat::Event),
+    RelayClient(relay::client::Event),
+    RelayServer(relay::Event),
     Mdns(mdns::Event),
 }
+
+// connection_limits and allow_block_list never emit events; they only deny
+// connections.
+impl From<Infallible> for PeernetBehaviourEvent {
+    fn from(event: Infallible) -> Self {
+        match event {}
+    }
+}
+
+impl From<identify::Event> for PeernetBehaviourEvent {
+    fn from(event: identify::Event) -> Self {
+        Self::Identify(Box::new(event))
+    }
+}
@@ -0,0 +1,162 @@
+use peernet_core::{NetworkEvent, TopicName};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+#[derive(Debug, Clone, Default)]
+pub enum EventFilter {
+    #[default]
+    All,
+    Topic(TopicName),
+    Gossip,
+    PeerLifecycle,
+    Dht,
+    Any(Vec<EventFilter>),
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &NetworkEvent) -> bool {
+        match self {
+            Self::All => true,
+            Self::Topic(topic) => {
+                matches!(event, NetworkEvent::GossipMessage { topic: t, .. } if t == topic)
+            }
+            Self::Gossip => matches!(
+                event,
+                NetworkEvent::GossipMessage { .. }
+                    | NetworkEvent::Subscribed { .. }
+                    | NetworkEvent::Unsubscribed { .. }
+                    | NetworkEvent::PeerSubscribed { .. }
+                    | NetworkEvent::PeerUnsubscribed { .. }
+            ),
+            Self::PeerLifecycle => matches!(
+                event,
+                NetworkEvent::PeerDiscovered { .. }
+                    | NetworkEvent::PeerConnected { .. }
+                    | NetworkEvent::PeerDisconnected { .. }
+                    | NetworkEvent::PeerIdentified { .. }
+                    | NetworkEvent::PeerBlocked { .. }
+                    | NetworkEvent::PeerUnblocked { .. }
+                    | NetworkEvent::PeerAllowed { .. }
+                    | NetworkEvent::PeerDisallowed { .. }
+                    | NetworkEvent::RelayReservationAccepted { .. }
+                    | NetworkEvent::RelayReservationGranted { .. }
+                    | NetworkEvent::RelayCircuitOpened { .. }
+                    | NetworkEvent::RelayedConnection { .. }
+                    | NetworkEvent::ConnectionLimitReached { .. }
+            ),
+            Self::Dht => matches!(
+                event,
+                NetworkEvent::RecordStored { .. }
+                    | NetworkEvent::RecordStoreFailed { .. }
+                    | NetworkEvent::RecordFound { .. }
+                    | NetworkEvent::RecordNotFound { .. }
+                    | NetworkEvent::RecordExpired { .. }
+                    | NetworkEvent::RecordPersistFailed { .. }
+                    | NetworkEvent::ProviderRecordStored { .. }
+                    | NetworkEvent::ProvidersFound { .. }
+                    | NetworkEvent::PeerFound { .. }
+                    | NetworkEvent::ClosestPeers { .. }
+                    | NetworkEvent::RoutingUpdated { .. }
+                    | NetworkEvent::BootstrapCompleted
+                    | NetworkEvent::BootstrapFailed { .. }
+            ),
+            Self::Any(filters) => filters.iter().any(|f| f.matches(event)),
+        }
+    }
+}
+
+// Each subscription owns a slot in a bounded broadcast ring. The actor never
+// waits on subscribers: one that falls more than the configured capacity
+// behind silently loses the oldest events, which are counted in `missed`.
+#[derive(Debug)]
+pub struct EventSubscription {
+    rx: broadcast::Receiver<NetworkEvent>,
+    filter: EventFilter,
+    missed: u64,
+}
+
+impl EventSubscription {
+    pub(crate) fn new(rx: broadcast::Receiver<NetworkEvent>, filter: EventFilter) -> Self {
+        Self {
+            rx,
+            filter,
+            missed: 0,
+        }
+    }
+
+    pub async fn recv(&mut self) -> Option<NetworkEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Some(event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "event subscriber lagged");
+                    self.missed += skipped;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    pub fn filter(&self) -> &EventFilter {
+        &self.filter
+    }
+
+    pub(crate) fn resubscribe(&self, filter: EventFilter) -> Self {
+        Self::new(self.rx.resubscribe(), filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peernet_core::{GossipPayload, PeerId};
+
+    fn message(topic: &str) -> NetworkEvent {
+        NetworkEvent::GossipMessage {
+            source: None,
+            topic: TopicName::new_unchecked(topic),
+            payload: GossipPayload::empty(),
+        }
+    }
+
+    #[test]
+    fn topic_filter_matches_only_its_topic() {
+        let filter = EventFilter::Topic(TopicName::new_unchecked("a"));
+        assert!(filter.matches(&message("a")));
+        assert!(!filter.matches(&message("b")));
+        assert!(!filter.matches(&NetworkEvent::ShutdownComplete));
+    }
+
+    #[test]
+    fn any_filter_combines() {
+        let filter = EventFilter::Any(vec![
+            EventFilter::Topic(TopicName::new_unchecked("a")),
+            EventFilter::PeerLifecycle,
+        ]);
+        let peer_id = PeerId::random();
+        assert!(filter.matches(&message("a")));
+        assert!(filter.matches(&NetworkEvent::PeerConnected { peer_id }));
+        assert!(!filter.matches(&NetworkEvent::RoutingUpdated { peer_id }));
+    }
+
+    #[tokio::test]
+    async fn lagged_subscriber_counts_missed_events() {
+        let (tx, rx) = broadcast::channel(2);
+        let mut sub = EventSubscription::new(rx, EventFilter::All);
+
+        for _ in 0..5 {
+            tx.send(NetworkEvent::ShutdownComplete).unwrap();
+        }
+        drop(tx);
+
+        assert!(sub.recv().await.is_some());
+        assert_eq!(sub.missed(), 3);
+        assert!(sub.recv().await.is_some());
+        assert!(sub.recv().await.is_none());
+    }
+}
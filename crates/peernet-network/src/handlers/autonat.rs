@@ -0,0 +1,43 @@
+use crate::behaviour::PeernetBehaviour;
+use crate::state::NetworkState;
+use crate::swarm::kad_mode;
+use libp2p::{Swarm, autonat};
+use peernet_core::{DhtMode, NetworkEvent, Reachability};
+use tracing::{debug, info};
+
+pub struct AutonatHandler;
+
+impl AutonatHandler {
+    pub fn handle(
+        state: &mut NetworkState,
+        swarm: &mut Swarm<PeernetBehaviour>,
+        event: autonat::Event,
+    ) {
+        let autonat::Event::StatusChanged { new, .. } = event else {
+            debug!(?event, "autonat probe");
+            return;
+        };
+        let reachability = match new {
+            autonat::NatStatus::Public(address) => Reachability::Public(address),
+            autonat::NatStatus::Private => Reachability::Private,
+            autonat::NatStatus::Unknown => Reachability::Unknown,
+        };
+
+        // Only a confirmed private node gives up serving; an unknown status
+        // usually means there was nobody to probe with yet.
+        let dht_mode = state.dht_mode.unwrap_or(match reachability {
+            Reachability::Private => DhtMode::Client,
+            Reachability::Public(_) | Reachability::Unknown => DhtMode::Server,
+        });
+        info!(%reachability, %dht_mode, "reachability changed");
+        swarm
+            .behaviour_mut()
+            .kademlia
+            .set_mode(Some(kad_mode(dht_mode)));
+
+        state.emit(NetworkEvent::ReachabilityChanged {
+            reachability,
+            dht_mode,
+        });
+    }
+}
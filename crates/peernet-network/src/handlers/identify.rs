@@ -0,0 +1,48 @@
+use crate::behaviour::PeernetBehaviour;
+use crate::state::NetworkState;
+use crate::swarm::KADEMLIA_PROTOCOL;
+use libp2p::{Swarm, identify};
+use peernet_core::NetworkEvent;
+
+pub struct IdentifyHandler;
+
+impl IdentifyHandler {
+    pub fn handle(
+        state: &mut NetworkState,
+        swarm: &mut Swarm<PeernetBehaviour>,
+        event: identify::Event,
+    ) {
+        let identify::Event::Received { peer_id, info, .. } = event else {
+            return;
+        };
+
+        // Only peers that speak our Kademlia protocol belong in the routing table.
+        if info
+            .protocols
+            .iter()
+            .any(|p| p.as_ref() == KADEMLIA_PROTOCOL)
+        {
+            for addr in &info.listen_addrs {
+                swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer_id, addr.clone());
+            }
+        }
+
+        let protocols: Vec<String> = info.protocols.iter().map(|p| p.to_string()).collect();
+        if let Some(peer) = state.peers.get_mut(&peer_id) {
+            peer.agent_version = Some(info.agent_version.clone());
+            peer.protocols = protocols.clone();
+        }
+
+        state.emit(NetworkEvent::PeerIdentified {
+            peer_id,
+            agent_version: info.agent_version,
+            protocol_version: info.protocol_version,
+            protocols,
+            listen_addrs: info.listen_addrs,
+            observed_addr: info.observed_addr,
+        });
+    }
+}
@@ -1,76 +1,270 @@
+use crate::behaviour::PeernetBehaviour;
 use crate::state::{NetworkState, PendingQuery};
-use libp2p::kad;
-use peernet_core::{DhtValue, NetworkEvent, PeerId};
+use libp2p::{Multiaddr, Swarm, kad, swarm::dial_opts::DialOpts};
+use peernet_core::{CommandError, DhtKey, DhtLookup, DhtValue, NetworkEvent, PeerId, Reply};
+use tracing::debug;
 
 pub struct KademliaHandler;
 
 impl KademliaHandler {
-    pub async fn handle(state: &mut NetworkState, event: kad::Event) {
+    pub fn handle(
+        state: &mut NetworkState,
+        swarm: &mut Swarm<PeernetBehaviour>,
+        event: kad::Event,
+    ) {
         match event {
+            kad::Event::OutboundQueryProgressed {
+                result: kad::QueryResult::Bootstrap(result),
+                step,
+                ..
+            } => match result {
+                Ok(_) if step.last => state.emit(NetworkEvent::BootstrapCompleted),
+                Ok(_) => {}
+                Err(err) => state.emit(NetworkEvent::BootstrapFailed {
+                    reason: err.to_string(),
+                }),
+            },
+            kad::Event::OutboundQueryProgressed {
+                id,
+                result: kad::QueryResult::GetClosestPeers(result),
+                ..
+            } => {
+                // A timed-out lookup still reports the closest peers it reached.
+                let peers = match result {
+                    Ok(kad::GetClosestPeersOk { peers, .. }) => peers,
+                    Err(kad::GetClosestPeersError::Timeout { peers, .. }) => peers,
+                };
+                match state.complete_query(&id) {
+                    Some(PendingQuery::FindPeer { peer_id, reply }) => {
+                        Self::finish_find(state, swarm, peer_id, peers, reply);
+                    }
+                    Some(PendingQuery::GetClosestPeers { key, reply }) => {
+                        let peers: Vec<PeerId> = peers.into_iter().map(|p| p.peer_id).collect();
+                        state.emit(NetworkEvent::ClosestPeers {
+                            key,
+                            peers: peers.clone(),
+                        });
+                        let _ = reply.send(Ok(peers));
+                    }
+                    _ => {}
+                }
+            }
             kad::Event::OutboundQueryProgressed { id, result, .. } => {
-                Self::handle_query_result(state, id, result).await;
+                Self::handle_query_result(state, swarm, id, result);
             }
             kad::Event::RoutingUpdated { peer, .. } => {
-                state
-                    .emit(NetworkEvent::RoutingUpdated { peer_id: peer })
-                    .await;
+                state.emit(NetworkEvent::RoutingUpdated { peer_id: peer });
             }
             _ => {}
         }
     }
 
-    async fn handle_query_result(
+    fn handle_query_result(
         state: &mut NetworkState,
+        swarm: &mut Swarm<PeernetBehaviour>,
         id: kad::QueryId,
         result: kad::QueryResult,
     ) {
         match result {
             kad::QueryResult::GetRecord(Ok(kad::GetRecordOk::FoundRecord(record))) => {
-                if let Some(PendingQuery::GetRecord(key)) = state.complete_query(&id) {
-                    let value =
-                        DhtValue::new(record.record.value).unwrap_or_else(|_| DhtValue::empty());
-                    state.emit(NetworkEvent::RecordFound { key, value }).await;
+                let Some(PendingQuery::GetRecord {
+                    required,
+                    values,
+                    local,
+                    ..
+                }) = state.query_mut(&id)
+                else {
+                    return;
+                };
+                // Records that don't decode are not a vote for anything.
+                let Ok(value) = DhtValue::new(record.record.value) else {
+                    return;
+                };
+                // Kad reports our own copy first, with no peer. It settles a
+                // single-record read on the spot, but a stricter quorum is
+                // about how many other peers agree.
+                if record.peer.is_none() && *required > 1 {
+                    *local = Some(value);
+                    return;
+                }
+                let agreeing = values.iter().filter(|v| **v == value).count() + 1;
+                values.push(value);
+                if agreeing < *required {
+                    return;
+                }
+                if let Some(mut query) = swarm.behaviour_mut().kademlia.query_mut(&id) {
+                    query.finish();
+                }
+                if let Some(PendingQuery::GetRecord {
+                    key,
+                    required,
+                    values,
+                    local,
+                    reply,
+                }) = state.complete_query(&id)
+                {
+                    Self::finish_get(state, key, values, local, required, reply);
                 }
             }
-            kad::QueryResult::GetRecord(Err(_)) => {
-                if let Some(PendingQuery::GetRecord(key)) = state.complete_query(&id) {
-                    state.emit(NetworkEvent::RecordNotFound { key }).await;
+            kad::QueryResult::GetRecord(Ok(kad::GetRecordOk::FinishedWithNoAdditionalRecord {
+                ..
+            }))
+            | kad::QueryResult::GetRecord(Err(_)) => {
+                if let Some(PendingQuery::GetRecord {
+                    key,
+                    required,
+                    values,
+                    local,
+                    reply,
+                }) = state.complete_query(&id)
+                {
+                    Self::finish_get(state, key, values, local, required, reply);
                 }
             }
             kad::QueryResult::PutRecord(Ok(kad::PutRecordOk { key: _ })) => {
-                if let Some(PendingQuery::PutRecord(dht_key)) = state.complete_query(&id) {
-                    state
-                        .emit(NetworkEvent::RecordStored { key: dht_key })
-                        .await;
+                if let Some(PendingQuery::PutRecord { key, reply }) = state.complete_query(&id) {
+                    state.emit(NetworkEvent::RecordStored { key });
+                    let _ = reply.send(Ok(()));
                 }
             }
             kad::QueryResult::PutRecord(Err(err)) => {
-                if let Some(PendingQuery::PutRecord(key)) = state.complete_query(&id) {
-                    state
-                        .emit(NetworkEvent::RecordStoreFailed {
-                            key,
-                            reason: format!("{err:?}"),
-                        })
-                        .await;
+                if let Some(PendingQuery::PutRecord { key, reply }) = state.complete_query(&id) {
+                    let reason = format!("{err:?}");
+                    let _ = reply.send(Err(CommandError::DhtFailed {
+                        key: key.to_string(),
+                        reason: reason.clone(),
+                    }));
+                    state.emit(NetworkEvent::RecordStoreFailed { key, reason });
                 }
             }
             kad::QueryResult::StartProviding(Ok(kad::AddProviderOk { key: _ })) => {
-                if let Some(PendingQuery::StartProviding(key)) = state.complete_query(&id) {
-                    state.emit(NetworkEvent::ProviderRecordStored { key }).await;
+                if let Some(PendingQuery::StartProviding { key, reply }) = state.complete_query(&id)
+                {
+                    state.emit(NetworkEvent::ProviderRecordStored { key });
+                    let _ = reply.send(Ok(()));
+                }
+            }
+            kad::QueryResult::StartProviding(Err(err)) => {
+                if let Some(PendingQuery::StartProviding { key, reply }) = state.complete_query(&id)
+                {
+                    let error = CommandError::DhtFailed {
+                        key: key.to_string(),
+                        reason: format!("{err:?}"),
+                    };
+                    let _ = reply.send(Err(error));
                 }
             }
             kad::QueryResult::GetProviders(Ok(kad::GetProvidersOk::FoundProviders {
                 providers,
                 ..
             })) => {
-                if let Some(PendingQuery::GetProviders(key)) = state.complete_query(&id) {
+                if let Some(PendingQuery::GetProviders { key, reply }) = state.complete_query(&id) {
                     let providers: Vec<PeerId> = providers.into_iter().collect();
-                    state
-                        .emit(NetworkEvent::ProvidersFound { key, providers })
-                        .await;
+                    state.emit(NetworkEvent::ProvidersFound {
+                        key,
+                        providers: providers.clone(),
+                    });
+                    let _ = reply.send(Ok(providers));
+                }
+            }
+            kad::QueryResult::GetProviders(Ok(
+                kad::GetProvidersOk::FinishedWithNoAdditionalRecord { .. },
+            )) => {
+                if let Some(PendingQuery::GetProviders { key, reply }) = state.complete_query(&id) {
+                    state.emit(NetworkEvent::ProvidersFound {
+                        key,
+                        providers: Vec::new(),
+                    });
+                    let _ = reply.send(Ok(Vec::new()));
+                }
+            }
+            kad::QueryResult::GetProviders(Err(err)) => {
+                if let Some(PendingQuery::GetProviders { key, reply }) = state.complete_query(&id) {
+                    let error = CommandError::DhtFailed {
+                        key: key.to_string(),
+                        reason: format!("{err:?}"),
+                    };
+                    let _ = reply.send(Err(error));
                 }
             }
             _ => {}
         }
     }
+
+    fn finish_find(
+        state: &NetworkState,
+        swarm: &mut Swarm<PeernetBehaviour>,
+        peer_id: PeerId,
+        peers: Vec<kad::PeerInfo>,
+        reply: Reply<Vec<Multiaddr>>,
+    ) {
+        let addresses = peers
+            .into_iter()
+            .find(|p| p.peer_id == peer_id)
+            .map(|p| p.addrs)
+            .unwrap_or_default();
+        if addresses.is_empty() {
+            let error = CommandError::PeerNotFound {
+                peer: peer_id.to_string(),
+            };
+            let _ = reply.send(Err(error));
+            return;
+        }
+
+        let opts = DialOpts::peer_id(peer_id)
+            .addresses(addresses.clone())
+            .build();
+        if let Err(e) = swarm.dial(opts) {
+            debug!(%peer_id, %e, "dial after lookup failed");
+        }
+        state.emit(NetworkEvent::PeerFound {
+            peer_id,
+            addresses: addresses.clone(),
+        });
+        let _ = reply.send(Ok(addresses));
+    }
+
+    fn finish_get(
+        state: &NetworkState,
+        key: DhtKey,
+        values: Vec<DhtValue>,
+        local: Option<DhtValue>,
+        required: usize,
+        reply: Reply<DhtLookup>,
+    ) {
+        let lookup = match DhtLookup::from_values(values) {
+            Some(lookup) if lookup.agreeing >= required => Some(lookup),
+            Some(lookup) => {
+                return Self::quorum_failed(key, lookup.agreeing, required, reply);
+            }
+            None if local.is_some() => {
+                return Self::quorum_failed(key, 0, required, reply);
+            }
+            None => None,
+        };
+
+        match lookup {
+            Some(lookup) => {
+                state.emit(NetworkEvent::RecordFound {
+                    key,
+                    value: lookup.value.clone(),
+                });
+                let _ = reply.send(Ok(lookup));
+            }
+            None => {
+                state.emit(NetworkEvent::RecordNotFound { key: key.clone() });
+                let _ = reply.send(Err(CommandError::RecordNotFound {
+                    key: key.to_string(),
+                }));
+            }
+        }
+    }
+
+    fn quorum_failed(key: DhtKey, found: usize, required: usize, reply: Reply<DhtLookup>) {
+        let _ = reply.send(Err(CommandError::QuorumFailed {
+            key: key.to_string(),
+            found,
+            required,
+        }));
+    }
 }
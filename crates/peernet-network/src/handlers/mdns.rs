@@ -6,17 +6,19 @@ use peernet_core::NetworkEvent;
 pub struct MdnsHandler;
 
 impl MdnsHandler {
-    pub async fn handle(
+    pub fn handle(
         state: &mut NetworkState,
         swarm: &mut Swarm<PeernetBehaviour>,
         event: mdns::Event,
     ) {
         match event {
             mdns::Event::Discovered(peers) => {
-                for (peer_id, addr) in peers.into_iter().filter(|(p, _)| {
-                    *p != state.local_peer_id && !state.connected_peers.contains(p)
-                }) {
-                    state.emit(NetworkEvent::PeerDiscovered { peer_id }).await;
+                for (peer_id, addr) in peers
+                    .into_iter()
+                    .filter(|(p, _)| *p != state.local_peer_id && !state.peers.contains_key(p))
+                    .filter(|(p, _)| state.peer_lists.permits(p))
+                {
+                    state.emit(NetworkEvent::PeerDiscovered { peer_id });
                     swarm
                         .behaviour_mut()
                         .kademlia
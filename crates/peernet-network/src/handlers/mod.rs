@@ -1,7 +1,15 @@
+mod autonat;
 mod gossipsub;
+mod identify;
 mod kademlia;
 mod mdns;
+mod ping;
+mod relay;
 
+pub use autonat::AutonatHandler;
 pub use gossipsub::GossipsubHandler;
+pub use identify::IdentifyHandler;
 pub use kademlia::KademliaHandler;
 pub use mdns::MdnsHandler;
+pub use ping::PingHandler;
+pub use relay::RelayHandler;
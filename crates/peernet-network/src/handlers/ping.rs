@@ -0,0 +1,35 @@
+use crate::state::NetworkState;
+use libp2p::ping;
+use peernet_core::{LatencyStats, NetworkEvent};
+use tracing::debug;
+
+pub struct PingHandler;
+
+impl PingHandler {
+    pub fn handle(state: &mut NetworkState, event: ping::Event) {
+        let ping::Event { peer, result, .. } = event;
+        let rtt = match result {
+            Ok(rtt) => rtt,
+            Err(e) => {
+                debug!(%peer, ?e, "ping failed");
+                return;
+            }
+        };
+
+        // A late ping can land after the connection closed.
+        let Some(info) = state.peers.get_mut(&peer) else {
+            return;
+        };
+        let stats = match &mut info.latency {
+            Some(stats) => {
+                stats.record(rtt);
+                *stats
+            }
+            latency @ None => *latency.insert(LatencyStats::new(rtt)),
+        };
+        state.emit(NetworkEvent::PeerLatency {
+            peer_id: peer,
+            stats,
+        });
+    }
+}
@@ -0,0 +1,65 @@
+use crate::state::NetworkState;
+use libp2p::relay;
+use peernet_core::NetworkEvent;
+use tracing::debug;
+
+pub struct RelayHandler;
+
+impl RelayHandler {
+    pub fn handle_client(state: &mut NetworkState, event: relay::client::Event) {
+        match event {
+            relay::client::Event::ReservationReqAccepted {
+                relay_peer_id,
+                renewal,
+                ..
+            } => {
+                state.emit(NetworkEvent::RelayReservationAccepted {
+                    relay_peer_id,
+                    renewal,
+                });
+            }
+            relay::client::Event::OutboundCircuitEstablished { relay_peer_id, .. } => {
+                debug!(%relay_peer_id, "outbound circuit established");
+            }
+            relay::client::Event::InboundCircuitEstablished { src_peer_id, .. } => {
+                debug!(%src_peer_id, "inbound circuit established");
+            }
+        }
+    }
+
+    pub fn handle_server(state: &mut NetworkState, event: relay::Event) {
+        match event {
+            relay::Event::ReservationReqAccepted {
+                src_peer_id,
+                renewed: false,
+            } => {
+                state.emit(NetworkEvent::RelayReservationGranted {
+                    peer_id: src_peer_id,
+                });
+            }
+            relay::Event::CircuitReqAccepted {
+                src_peer_id,
+                dst_peer_id,
+            } => {
+                state.emit(NetworkEvent::RelayCircuitOpened {
+                    src_peer_id,
+                    dst_peer_id,
+                });
+            }
+            relay::Event::ReservationReqDenied {
+                src_peer_id,
+                status,
+            } => {
+                debug!(%src_peer_id, ?status, "relay reservation denied");
+            }
+            relay::Event::CircuitReqDenied {
+                src_peer_id,
+                dst_peer_id,
+                status,
+            } => {
+                debug!(%src_peer_id, %dst_peer_id, ?status, "relay circuit denied");
+            }
+            _ => {}
+        }
+    }
+}
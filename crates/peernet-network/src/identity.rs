@@ -0,0 +1,193 @@
+use libp2p::{identity::Keypair, pnet::PreSharedKey};
+use peernet_core::{PeernetError, PeernetResult};
+use std::{fs, io::Write, path::Path};
+use tracing::{info, warn};
+
+pub fn load_or_generate_identity(path: &Path) -> PeernetResult<Keypair> {
+    if path.exists() {
+        return load_identity(path);
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    save_identity(path, &keypair)?;
+    info!(path = %path.display(), "generated new identity");
+    Ok(keypair)
+}
+
+pub fn load_identity(path: &Path) -> PeernetResult<Keypair> {
+    let bytes = fs::read(path).map_err(|e| identity_error(path, e.to_string()))?;
+    warn_if_readable_by_others(path, "identity");
+
+    ensure_ed25519(path, &bytes)?;
+    Keypair::from_protobuf_encoding(&bytes)
+        .map_err(|e| identity_error(path, format!("corrupt or unsupported key file: {e}")))
+}
+
+// The key type is read from the protobuf header rather than the decoded key,
+// so other key types get a clear error even when libp2p is built without
+// support for them. Anything that isn't a header is left to the decoder.
+fn ensure_ed25519(path: &Path, bytes: &[u8]) -> PeernetResult<()> {
+    const KEY_TYPE_FIELD: u8 = 0x08;
+    let key_type = match bytes {
+        [KEY_TYPE_FIELD, key_type, ..] => *key_type,
+        _ => return Ok(()),
+    };
+    let name = match key_type {
+        1 => return Ok(()),
+        0 => "RSA",
+        2 => "secp256k1",
+        3 => "ECDSA",
+        _ => "unknown",
+    };
+    Err(identity_error(
+        path,
+        format!("identity must be an ed25519 key, found {name}"),
+    ))
+}
+
+// Expects the go-libp2p swarm.key format: a `/key/swarm/psk/1.0.0/` header,
+// `/base16/` and 64 hex digits.
+pub fn load_psk(path: &Path) -> PeernetResult<PreSharedKey> {
+    let contents = fs::read_to_string(path).map_err(|e| psk_error(path, e))?;
+    warn_if_readable_by_others(path, "pre-shared key");
+
+    contents
+        .parse()
+        .map_err(|e| psk_error(path, format!("invalid pre-shared key: {e}")))
+}
+
+pub fn save_identity(path: &Path, keypair: &Keypair) -> PeernetResult<()> {
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .map_err(|e| identity_error(path, e.to_string()))?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| identity_error(path, e.to_string()))?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .map_err(|e| identity_error(path, e.to_string()))?;
+    file.write_all(&bytes)
+        .and_then(|()| file.sync_all())
+        .map_err(|e| identity_error(path, e.to_string()))
+}
+
+// `kind` names what the file holds, for the warning.
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path, kind: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = fs::metadata(path)
+        && metadata.permissions().mode() & 0o077 != 0
+    {
+        warn!(path = %path.display(), "{kind} file is accessible by other users");
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &Path, _kind: &str) {}
+
+fn identity_error(path: &Path, reason: String) -> PeernetError {
+    PeernetError::Identity {
+        path: path.display().to_string(),
+        reason,
+    }
+}
+
+fn psk_error(path: &Path, reason: impl std::fmt::Display) -> PeernetError {
+    PeernetError::Config {
+        reason: format!("pre-shared key {}: {reason}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::PeerId;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("peernet-identity-{}", PeerId::random()))
+            .join(name)
+    }
+
+    #[test]
+    fn generates_then_reloads_same_identity() {
+        let path = temp_path("node.key");
+
+        let first = load_or_generate_identity(&path).unwrap();
+        let second = load_or_generate_identity(&path).unwrap();
+
+        assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn generated_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_path("node.key");
+
+        load_or_generate_identity(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rejects_corrupt_file() {
+        let path = temp_path("corrupt.key");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"not a key").unwrap();
+
+        let result = load_or_generate_identity(&path);
+
+        assert!(matches!(result, Err(PeernetError::Identity { .. })));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rejects_non_ed25519_key() {
+        let path = temp_path("secp256k1.key");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut encoded = vec![0x08, 0x02, 0x12, 0x20];
+        encoded.extend_from_slice(&[7u8; 32]);
+        fs::write(&path, encoded).unwrap();
+
+        let result = load_identity(&path);
+
+        assert!(matches!(
+            result,
+            Err(PeernetError::Identity { ref reason, .. }) if reason.contains("must be an ed25519 key")
+        ));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn loads_swarm_key_file() {
+        let path = temp_path("swarm.key");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let psk = PreSharedKey::new([42; 32]);
+        fs::write(&path, psk.to_string()).unwrap();
+
+        let loaded = load_psk(&path).unwrap();
+        assert_eq!(
+            loaded.fingerprint().to_string(),
+            psk.fingerprint().to_string()
+        );
+
+        fs::write(&path, "/key/swarm/psk/1.0.0/\n/base16/\nabcd\n").unwrap();
+        assert!(matches!(load_psk(&path), Err(PeernetError::Config { .. })));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,32 +1,192 @@
 mod behaviour;
+mod events;
 mod handlers;
+mod identity;
+mod metrics;
+mod peer_lists;
 mod state;
+mod store;
 mod swarm;
 
 use behaviour::{PeernetBehaviour, PeernetBehaviourEvent};
 use futures::StreamExt;
-use handlers::{GossipsubHandler, KademliaHandler, MdnsHandler};
-use libp2p::{gossipsub, kad::RecordKey, swarm::SwarmEvent};
+use handlers::{
+    AutonatHandler, GossipsubHandler, IdentifyHandler, KademliaHandler, MdnsHandler, PingHandler,
+    RelayHandler,
+};
+use libp2p::{
+    connection_limits::Exceeded,
+    core::{ConnectedPoint, transport::ListenerId},
+    gossipsub,
+    kad::{self, RecordKey, store::RecordStore},
+    metrics::{Metrics, Recorder},
+    multiaddr::Protocol,
+    swarm::{DialError, ListenError, SwarmEvent},
+};
+use metrics::{PeernetMetrics, serve_metrics};
+use peer_lists::{PeerLists, Persisted};
 use peernet_core::{
-    CommandError, DhtKey, DhtValue, GossipPayload, Multiaddr, NetworkCommand, NetworkEvent,
-    PeernetError, PeernetResult, TopicName,
+    CommandError, ConnectionDirection, DhtKey, DhtLookup, DhtQuorum, DhtValue, GossipPayload,
+    LatencyStats, Multiaddr, NetworkCommand, NetworkEvent, PeerId, PeerInfo, PeernetError,
+    PeernetResult, Reply, TopicName,
 };
+use prometheus_client::registry::Registry;
 use state::{NetworkState, PendingQuery};
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use swarm::{DEFAULT_TOPIC, build_swarm};
-use tokio::sync::mpsc;
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc, oneshot},
+};
 use tokio_util::sync::CancellationToken;
 use tracing::warn;
 
-#[derive(Debug, Clone, Default)]
+// How long startup waits for more listen addresses once each listener has
+// reported one.
+const LISTEN_SETTLE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
 pub struct NetworkConfig {
     pub port: u16,
+    pub transport: TransportConfig,
+    pub bootstrap_peers: Vec<Multiaddr>,
+    pub relays: Vec<Multiaddr>,
+    pub event_capacity: usize,
+    pub record_expiry_interval: Duration,
+    pub metrics_addr: Option<SocketAddr>,
     pub swarm: SwarmConfig,
 }
 
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            transport: TransportConfig::default(),
+            bootstrap_peers: Vec::new(),
+            relays: Vec::new(),
+            event_capacity: 1024,
+            record_expiry_interval: Duration::from_secs(30),
+            metrics_addr: None,
+            swarm: SwarmConfig::default(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    // A pre-shared key only wraps TCP, so any other transport asked for
+    // alongside one would silently never come up.
+    pub fn validate(&self) -> PeernetResult<()> {
+        if self.swarm.psk.is_none() {
+            return Ok(());
+        }
+        let transport = &self.transport;
+        for (name, enabled) in [
+            ("quic", transport.quic),
+            ("websocket", transport.websocket),
+            ("memory", transport.memory),
+        ] {
+            if enabled {
+                return Err(PeernetError::Config {
+                    reason: format!(
+                        "transport.{name} can't be used with a pre-shared key; a private network runs over TCP only"
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Listens on the in-process memory transport only, with mDNS off, so
+    // peers have to be connected explicitly through `bootstrap_peers` or
+    // dialing. Nodes built this way never touch a socket and can run in
+    // parallel.
+    pub fn memory() -> Self {
+        Self {
+            transport: TransportConfig {
+                tcp: false,
+                quic: false,
+                memory: true,
+                ..Default::default()
+            },
+            swarm: SwarmConfig {
+                enable_mdns: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PutOptions {
+    pub ttl: Option<Duration>,
+    pub quorum: DhtQuorum,
+}
+
+// WebSocket needs a TCP port of its own; zero picks any free one. `memory`
+// only reaches nodes in the same process.
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    pub tcp: bool,
+    pub quic: bool,
+    pub websocket: bool,
+    pub websocket_port: u16,
+    pub memory: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            tcp: true,
+            quic: true,
+            websocket: false,
+            websocket_port: 0,
+            memory: false,
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn listen_addrs(&self, port: u16) -> Vec<Multiaddr> {
+        let mut addrs = Vec::new();
+        if self.tcp {
+            addrs.push(format!("/ip4/0.0.0.0/tcp/{port}"));
+        }
+        if self.quic {
+            addrs.push(format!("/ip4/0.0.0.0/udp/{port}/quic-v1"));
+        }
+        if self.websocket {
+            addrs.push(format!("/ip4/0.0.0.0/tcp/{}/ws", self.websocket_port));
+        }
+        if self.memory {
+            addrs.push("/memory/0".to_string());
+        }
+        addrs
+            .into_iter()
+            .map(|a| a.parse().expect("valid multiaddr"))
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct NetworkHandle {
     command_tx: mpsc::Sender<NetworkCommand>,
-    event_rx: mpsc::Receiver<NetworkEvent>,
+    events: EventSubscription,
+}
+
+impl Clone for NetworkHandle {
+    fn clone(&self) -> Self {
+        Self {
+            command_tx: self.command_tx.clone(),
+            events: self.events.resubscribe(EventFilter::All),
+        }
+    }
 }
 
 impl NetworkHandle {
@@ -38,45 +198,208 @@ impl NetworkHandle {
     }
 
     pub async fn recv(&mut self) -> Option<NetworkEvent> {
-        self.event_rx.recv().await
+        self.events.recv().await
+    }
+
+    pub fn subscribe_events(&self, filter: EventFilter) -> EventSubscription {
+        self.events.resubscribe(filter)
     }
 
     pub async fn shutdown(&self) -> PeernetResult<()> {
         self.send(NetworkCommand::Shutdown).await
     }
 
-    pub async fn publish(&self, payload: GossipPayload) -> PeernetResult<()> {
-        self.send(NetworkCommand::Publish {
-            topic: TopicName::new_unchecked(DEFAULT_TOPIC),
+    pub async fn dial(&self, addr: Multiaddr) -> PeernetResult<()> {
+        self.send(NetworkCommand::Dial { addr }).await
+    }
+
+    pub fn publish(
+        &self,
+        payload: GossipPayload,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.publish_to(TopicName::new_unchecked(DEFAULT_TOPIC), payload)
+    }
+
+    pub fn publish_to(
+        &self,
+        topic: TopicName,
+        payload: GossipPayload,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(|reply| NetworkCommand::Publish {
+            topic,
             payload,
+            reply,
+        })
+    }
+
+    pub fn subscribe(&self, topic: TopicName) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(|reply| NetworkCommand::Subscribe { topic, reply })
+    }
+
+    pub fn unsubscribe(&self, topic: TopicName) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(|reply| NetworkCommand::Unsubscribe { topic, reply })
+    }
+
+    pub fn subscriptions(&self) -> impl Future<Output = PeernetResult<Vec<TopicName>>> + use<> {
+        self.request(|reply| NetworkCommand::ListSubscriptions { reply })
+    }
+
+    pub fn connected_peers(&self) -> impl Future<Output = PeernetResult<Vec<PeerId>>> + use<> {
+        self.request(|reply| NetworkCommand::ListPeers { reply })
+    }
+
+    pub fn latency(
+        &self,
+        peer_id: PeerId,
+    ) -> impl Future<Output = PeernetResult<Option<LatencyStats>>> + use<> {
+        self.request(move |reply| NetworkCommand::GetLatency { peer_id, reply })
+    }
+
+    pub fn peers(&self) -> impl Future<Output = PeernetResult<Vec<PeerInfo>>> + use<> {
+        self.request(|reply| NetworkCommand::ListPeerInfo { reply })
+    }
+
+    pub fn block_peer(&self, peer_id: PeerId) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(move |reply| NetworkCommand::BlockPeer { peer_id, reply })
+    }
+
+    pub fn unblock_peer(&self, peer_id: PeerId) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(move |reply| NetworkCommand::UnblockPeer { peer_id, reply })
+    }
+
+    pub fn allow_peer(&self, peer_id: PeerId) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(move |reply| NetworkCommand::AllowPeer { peer_id, reply })
+    }
+
+    pub fn disallow_peer(
+        &self,
+        peer_id: PeerId,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(move |reply| NetworkCommand::DisallowPeer { peer_id, reply })
+    }
+
+    pub fn blocked_peers(&self) -> impl Future<Output = PeernetResult<Vec<PeerId>>> + use<> {
+        self.request(|reply| NetworkCommand::ListBlockedPeers { reply })
+    }
+
+    pub fn put(
+        &self,
+        key: DhtKey,
+        value: DhtValue,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.put_with(key, value, PutOptions::default())
+    }
+
+    pub fn put_with_ttl(
+        &self,
+        key: DhtKey,
+        value: DhtValue,
+        ttl: Duration,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        let options = PutOptions {
+            ttl: Some(ttl),
+            ..Default::default()
+        };
+        self.put_with(key, value, options)
+    }
+
+    pub fn put_with(
+        &self,
+        key: DhtKey,
+        value: DhtValue,
+        options: PutOptions,
+    ) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(move |reply| NetworkCommand::PutRecord {
+            key,
+            value,
+            ttl: options.ttl,
+            quorum: options.quorum,
+            reply,
         })
-        .await
     }
 
-    pub async fn put(&self, key: DhtKey, value: DhtValue) -> PeernetResult<()> {
-        self.send(NetworkCommand::PutRecord { key, value }).await
+    pub fn get(&self, key: DhtKey) -> impl Future<Output = PeernetResult<DhtValue>> + use<> {
+        let lookup = self.get_with_quorum(key, DhtQuorum::One);
+        async move { lookup.await.map(|lookup| lookup.value) }
+    }
+
+    pub fn get_with_quorum(
+        &self,
+        key: DhtKey,
+        quorum: DhtQuorum,
+    ) -> impl Future<Output = PeernetResult<DhtLookup>> + use<> {
+        self.request(move |reply| NetworkCommand::GetRecord { key, quorum, reply })
+    }
+
+    pub fn start_providing(&self, key: DhtKey) -> impl Future<Output = PeernetResult<()>> + use<> {
+        self.request(|reply| NetworkCommand::StartProviding { key, reply })
+    }
+
+    pub fn get_providers(
+        &self,
+        key: DhtKey,
+    ) -> impl Future<Output = PeernetResult<Vec<PeerId>>> + use<> {
+        self.request(|reply| NetworkCommand::GetProviders { key, reply })
+    }
+
+    // Looks the peer up in the DHT and dials it at the addresses found.
+    pub fn find_peer(
+        &self,
+        peer_id: PeerId,
+    ) -> impl Future<Output = PeernetResult<Vec<Multiaddr>>> + use<> {
+        self.request(move |reply| NetworkCommand::FindPeer { peer_id, reply })
+    }
+
+    pub fn closest_peers(
+        &self,
+        key: DhtKey,
+    ) -> impl Future<Output = PeernetResult<Vec<PeerId>>> + use<> {
+        self.request(|reply| NetworkCommand::GetClosestPeers { key, reply })
     }
 
-    pub async fn get(&self, key: DhtKey) -> PeernetResult<()> {
-        self.send(NetworkCommand::GetRecord { key }).await
+    fn request<T, F>(&self, build: F) -> impl Future<Output = PeernetResult<T>> + use<T, F>
+    where
+        F: FnOnce(Reply<T>) -> NetworkCommand,
+    {
+        let command_tx = self.command_tx.clone();
+        let (reply, reply_rx) = oneshot::channel();
+        let cmd = build(reply);
+        async move {
+            command_tx
+                .send(cmd)
+                .await
+                .map_err(|_| PeernetError::SendFailed { actor: "network" })?;
+            let result = reply_rx.await.map_err(|_| PeernetError::ChannelClosed {
+                actor: "network",
+                reason: "dropped reply",
+            })?;
+            Ok(result?)
+        }
     }
 }
 
 pub fn spawn(config: NetworkConfig, cancel_token: CancellationToken) -> NetworkHandle {
     let (command_tx, command_rx) = mpsc::channel::<NetworkCommand>(32);
-    let (event_tx, event_rx) = mpsc::channel::<NetworkEvent>(32);
+    let (event_tx, event_rx) = broadcast::channel::<NetworkEvent>(config.event_capacity);
     tokio::spawn(run_network_loop(config, command_rx, event_tx, cancel_token));
     NetworkHandle {
         command_tx,
-        event_rx,
+        events: EventSubscription::new(event_rx, EventFilter::All),
     }
 }
 
-pub use swarm::SwarmConfig;
+pub use events::{EventFilter, EventSubscription};
+pub use identity::{load_identity, load_or_generate_identity, load_psk, save_identity};
+pub use libp2p::pnet::PreSharedKey;
+pub use peer_lists::PeerListsConfig;
+pub use store::{RecordStoreBackend, RecordStoreConfig};
+pub use swarm::{AutonatConfig, ConnectionLimitsConfig, RelayServerConfig, SwarmConfig};
 
 struct NetworkActor {
     swarm: libp2p::Swarm<PeernetBehaviour>,
     state: NetworkState,
+    replication: NonZeroUsize,
+    metrics: Option<Metrics>,
 }
 
 enum CommandOutcome {
@@ -84,8 +407,71 @@ enum CommandOutcome {
     Shutdown,
 }
 
+// A command with a reply reports its failure only to the caller; the loop
+// broadcasts `CommandFailed` just for commands nobody is waiting on.
+fn reject<T>(reply: Reply<T>, error: CommandError) -> Result<CommandOutcome, CommandError> {
+    let _ = reply.send(Err(error));
+    Ok(CommandOutcome::Continue)
+}
+
 impl NetworkActor {
+    // Reservations carry the relay's external addresses and clients refuse
+    // ones without any, so a relay server advertises where it listens.
+    fn advertise_to_relay_clients(&mut self, address: &Multiaddr) {
+        if self.swarm.behaviour().relay_server.is_enabled()
+            && !address.iter().any(|p| p == Protocol::P2pCircuit)
+        {
+            self.swarm.add_external_address(address.clone());
+        }
+    }
+
+    fn bootstrap(&mut self, peers: &[Multiaddr]) {
+        for addr in peers {
+            let Some(peer_id) = peer_id_of(addr) else {
+                warn!(%addr, "bootstrap address is missing its /p2p peer id");
+                continue;
+            };
+            self.swarm
+                .behaviour_mut()
+                .kademlia
+                .add_address(&peer_id, addr.clone());
+            if let Err(e) = self.swarm.dial(addr.clone()) {
+                warn!(%addr, ?e, "failed to dial bootstrap peer");
+            }
+        }
+
+        if let Err(e) = self.swarm.behaviour_mut().kademlia.bootstrap() {
+            self.state.emit(NetworkEvent::BootstrapFailed {
+                reason: e.to_string(),
+            });
+        }
+    }
+
+    fn expire_records(&mut self) {
+        let now = Instant::now();
+        let store = self.swarm.behaviour_mut().kademlia.store_mut();
+        let expired: Vec<RecordKey> = store
+            .records()
+            .filter(|record| record.is_expired(now))
+            .map(|record| record.key.clone())
+            .collect();
+
+        for record_key in expired {
+            store.remove(&record_key);
+            let key = std::str::from_utf8(record_key.as_ref())
+                .ok()
+                .and_then(|k| DhtKey::new(k).ok());
+            if let Some(key) = key {
+                self.state.emit(NetworkEvent::RecordExpired { key });
+            }
+        }
+    }
+
     fn handle_command(&mut self, cmd: NetworkCommand) -> Result<CommandOutcome, CommandError> {
+        if let Some(metrics) = &self.state.metrics {
+            metrics.command_handled(&cmd);
+        }
+
         match cmd {
             NetworkCommand::Shutdown => return Ok(CommandOutcome::Shutdown),
 
@@ -98,91 +484,214 @@ impl NetworkActor {
                     })?;
             }
 
-            NetworkCommand::Subscribe { topic } => {
+            NetworkCommand::Subscribe { topic, reply } => {
                 let ident = gossipsub::IdentTopic::new(topic.as_ref());
-                self.swarm
-                    .behaviour_mut()
-                    .gossipsub
-                    .subscribe(&ident)
-                    .map_err(|e| CommandError::SubscribeFailed {
-                        topic: topic.to_string(),
-                        reason: e.to_string(),
-                    })?;
+                if let Err(e) = self.swarm.behaviour_mut().gossipsub.subscribe(&ident) {
+                    return reject(
+                        reply,
+                        CommandError::SubscribeFailed {
+                            topic: topic.to_string(),
+                            reason: e.to_string(),
+                        },
+                    );
+                }
                 self.state.add_subscription(&topic);
+                self.state.emit(NetworkEvent::Subscribed { topic });
+                let _ = reply.send(Ok(()));
             }
 
-            NetworkCommand::Unsubscribe { topic } => {
+            NetworkCommand::Unsubscribe { topic, reply } => {
                 let ident = gossipsub::IdentTopic::new(topic.as_ref());
                 if !self.swarm.behaviour_mut().gossipsub.unsubscribe(&ident) {
-                    return Err(CommandError::UnsubscribeFailed {
-                        topic: topic.to_string(),
-                        reason: "not subscribed".into(),
-                    });
+                    return reject(
+                        reply,
+                        CommandError::UnsubscribeFailed {
+                            topic: topic.to_string(),
+                            reason: "not subscribed".into(),
+                        },
+                    );
                 }
                 self.state.remove_subscription(&topic);
+                self.state.emit(NetworkEvent::Unsubscribed { topic });
+                let _ = reply.send(Ok(()));
             }
 
-            NetworkCommand::Publish { topic, payload } => {
+            NetworkCommand::ListSubscriptions { reply } => {
+                let _ = reply.send(Ok(self.state.subscriptions()));
+            }
+
+            NetworkCommand::ListPeers { reply } => {
+                let mut peers: Vec<PeerId> = self.state.peers.keys().copied().collect();
+                peers.sort();
+                let _ = reply.send(Ok(peers));
+            }
+
+            NetworkCommand::ListPeerInfo { reply } => {
+                let mut peers: Vec<PeerInfo> = self.state.peers.values().cloned().collect();
+                peers.sort_by_key(|info| info.peer_id);
+                let _ = reply.send(Ok(peers));
+            }
+
+            NetworkCommand::GetLatency { peer_id, reply } => {
+                let latency = self.state.peers.get(&peer_id).and_then(|info| info.latency);
+                let _ = reply.send(Ok(latency));
+            }
+
+            NetworkCommand::BlockPeer { peer_id, reply } => {
+                let persisted = self.state.peer_lists.block(peer_id);
+                let behaviour = self.swarm.behaviour_mut();
+                behaviour.blocked.block_peer(peer_id);
+                behaviour.gossipsub.blacklist_peer(&peer_id);
+                behaviour.kademlia.remove_peer(&peer_id);
+                self.state.emit(NetworkEvent::PeerBlocked { peer_id });
+                reply_when_persisted(peer_id, persisted, reply);
+            }
+
+            NetworkCommand::UnblockPeer { peer_id, reply } => {
+                let persisted = self.state.peer_lists.unblock(peer_id);
+                let behaviour = self.swarm.behaviour_mut();
+                behaviour.blocked.unblock_peer(peer_id);
+                behaviour.gossipsub.remove_blacklisted_peer(&peer_id);
+                self.state.emit(NetworkEvent::PeerUnblocked { peer_id });
+                reply_when_persisted(peer_id, persisted, reply);
+            }
+
+            NetworkCommand::AllowPeer { peer_id, reply } => {
+                let persisted = self.state.peer_lists.allow(peer_id);
+                if let Some(allowed) = self.swarm.behaviour_mut().allowed.as_mut() {
+                    allowed.allow_peer(peer_id);
+                }
+                self.state.emit(NetworkEvent::PeerAllowed { peer_id });
+                reply_when_persisted(peer_id, persisted, reply);
+            }
+
+            NetworkCommand::DisallowPeer { peer_id, reply } => {
+                let persisted = self.state.peer_lists.disallow(peer_id);
+                if let Some(allowed) = self.swarm.behaviour_mut().allowed.as_mut() {
+                    allowed.disallow_peer(peer_id);
+                }
+                self.state.emit(NetworkEvent::PeerDisallowed { peer_id });
+                reply_when_persisted(peer_id, persisted, reply);
+            }
+
+            NetworkCommand::ListBlockedPeers { reply } => {
+                let _ = reply.send(Ok(self.state.peer_lists.blocked().collect()));
+            }
+
+            NetworkCommand::Publish {
+                topic,
+                payload,
+                reply,
+            } => {
                 if !self.state.is_subscribed(&topic) {
-                    return Err(CommandError::PublishFailed {
-                        topic: topic.to_string(),
-                        reason: "not subscribed".into(),
-                    });
+                    return reject(
+                        reply,
+                        CommandError::PublishFailed {
+                            topic: topic.to_string(),
+                            reason: "not subscribed".into(),
+                        },
+                    );
                 }
                 let ident = gossipsub::IdentTopic::new(topic.as_ref());
-                self.swarm
+                if let Err(e) = self
+                    .swarm
                     .behaviour_mut()
                     .gossipsub
-                    .publish(ident, payload.as_bytes().to_vec())
-                    .map_err(|e| CommandError::PublishFailed {
-                        topic: topic.to_string(),
-                        reason: e.to_string(),
-                    })?;
+                    .publish(ident, payload.into_bytes())
+                {
+                    return reject(
+                        reply,
+                        CommandError::PublishFailed {
+                            topic: topic.to_string(),
+                            reason: e.to_string(),
+                        },
+                    );
+                }
+                let _ = reply.send(Ok(()));
             }
 
-            NetworkCommand::PutRecord { key, value } => {
+            NetworkCommand::PutRecord {
+                key,
+                value,
+                ttl,
+                quorum,
+                reply,
+            } => {
                 let record = libp2p::kad::Record {
                     key: RecordKey::new(&key.as_str()),
                     value: value.into_bytes(),
                     publisher: Some(self.state.local_peer_id),
-                    expires: None,
+                    expires: ttl.map(|ttl| Instant::now() + ttl),
                 };
-                let query_id = self
+                match self
                     .swarm
                     .behaviour_mut()
                     .kademlia
-                    .put_record(record, libp2p::kad::Quorum::One)
-                    .map_err(|e| CommandError::DhtFailed {
-                        key: key.to_string(),
-                        reason: e.to_string(),
-                    })?;
-                self.state
-                    .track_query(query_id, PendingQuery::PutRecord(key));
+                    .put_record(record, kad_quorum(quorum))
+                {
+                    Ok(query_id) => {
+                        self.state
+                            .track_query(query_id, PendingQuery::PutRecord { key, reply });
+                    }
+                    // Reported like a failed replication, so RecordStoreFailed
+                    // covers every way a put can fail.
+                    Err(e) => {
+                        let reason = e.to_string();
+                        self.state.emit(NetworkEvent::RecordStoreFailed {
+                            key: key.clone(),
+                            reason: reason.clone(),
+                        });
+                        return reject(
+                            reply,
+                            CommandError::DhtFailed {
+                                key: key.to_string(),
+                                reason,
+                            },
+                        );
+                    }
+                }
             }
 
-            NetworkCommand::GetRecord { key } => {
+            NetworkCommand::GetRecord { key, quorum, reply } => {
                 let record_key = RecordKey::new(&key.as_str());
                 let query_id = self.swarm.behaviour_mut().kademlia.get_record(record_key);
-                self.state
-                    .track_query(query_id, PendingQuery::GetRecord(key));
+                self.state.track_query(
+                    query_id,
+                    PendingQuery::GetRecord {
+                        key,
+                        required: quorum.required(self.replication.get()),
+                        values: Vec::new(),
+                        local: None,
+                        reply,
+                    },
+                );
             }
 
-            NetworkCommand::StartProviding { key } => {
+            NetworkCommand::StartProviding { key, reply } => {
                 let record_key = RecordKey::new(&key.as_str());
-                let query_id = self
+                match self
                     .swarm
                     .behaviour_mut()
                     .kademlia
                     .start_providing(record_key)
-                    .map_err(|e| CommandError::DhtFailed {
-                        key: key.to_string(),
-                        reason: e.to_string(),
-                    })?;
-                self.state
-                    .track_query(query_id, PendingQuery::StartProviding(key));
+                {
+                    Ok(query_id) => {
+                        self.state
+                            .track_query(query_id, PendingQuery::StartProviding { key, reply });
+                    }
+                    Err(e) => {
+                        return reject(
+                            reply,
+                            CommandError::DhtFailed {
+                                key: key.to_string(),
+                                reason: e.to_string(),
+                            },
+                        );
+                    }
+                }
             }
 
-            NetworkCommand::GetProviders { key } => {
+            NetworkCommand::GetProviders { key, reply } => {
                 let record_key = RecordKey::new(&key.as_str());
                 let query_id = self
                     .swarm
@@ -190,109 +699,398 @@ impl NetworkActor {
                     .kademlia
                     .get_providers(record_key);
                 self.state
-                    .track_query(query_id, PendingQuery::GetProviders(key));
+                    .track_query(query_id, PendingQuery::GetProviders { key, reply });
+            }
+
+            NetworkCommand::FindPeer { peer_id, reply } => {
+                // Nothing to look up for a peer we are already talking to.
+                if let Some(info) = self.state.peers.get(&peer_id) {
+                    let addresses = info.addresses.clone();
+                    self.state.emit(NetworkEvent::PeerFound {
+                        peer_id,
+                        addresses: addresses.clone(),
+                    });
+                    let _ = reply.send(Ok(addresses));
+                    return Ok(CommandOutcome::Continue);
+                }
+                let query_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .get_closest_peers(peer_id);
+                self.state
+                    .track_query(query_id, PendingQuery::FindPeer { peer_id, reply });
+            }
+
+            NetworkCommand::GetClosestPeers { key, reply } => {
+                let query_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .get_closest_peers(RecordKey::new(&key.as_str()).to_vec());
+                self.state
+                    .track_query(query_id, PendingQuery::GetClosestPeers { key, reply });
             }
         }
         Ok(CommandOutcome::Continue)
     }
 
-    async fn handle_swarm_event(&mut self, event: SwarmEvent<PeernetBehaviourEvent>) {
+    fn handle_swarm_event(&mut self, event: SwarmEvent<PeernetBehaviourEvent>) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record(&event);
+            match &event {
+                SwarmEvent::Behaviour(PeernetBehaviourEvent::Kademlia(e)) => metrics.record(e),
+                SwarmEvent::Behaviour(PeernetBehaviourEvent::Gossipsub(e)) => metrics.record(e),
+                SwarmEvent::Behaviour(PeernetBehaviourEvent::Identify(e)) => {
+                    metrics.record(e.as_ref())
+                }
+                SwarmEvent::Behaviour(PeernetBehaviourEvent::Ping(e)) => metrics.record(e),
+                SwarmEvent::Behaviour(PeernetBehaviourEvent::RelayServer(e)) => metrics.record(e),
+                _ => {}
+            }
+        }
+
         match event {
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                endpoint,
+                num_established,
+                ..
+            } if num_established.get() == 1 => {
+                let address = endpoint.get_remote_address().clone();
+                let direction = if endpoint.is_dialer() {
+                    ConnectionDirection::Dialer
+                } else {
+                    ConnectionDirection::Listener
+                };
+                self.state
+                    .peers
+                    .insert(peer_id, PeerInfo::new(peer_id, address.clone(), direction));
+                self.swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .add_explicit_peer(&peer_id);
+                self.swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer_id, address);
+                self.state.emit(NetworkEvent::PeerConnected { peer_id });
+                let circuit_addr = match &endpoint {
+                    ConnectedPoint::Dialer { address, .. } => address,
+                    ConnectedPoint::Listener { local_addr, .. } => local_addr,
+                };
+                if let Some(relay_peer_id) = relay_of(circuit_addr) {
+                    self.state.emit(NetworkEvent::RelayedConnection {
+                        peer_id,
+                        relay_peer_id,
+                    });
+                }
+            }
+
             SwarmEvent::ConnectionEstablished {
                 peer_id,
                 endpoint,
                 num_established,
                 ..
             } => {
-                if num_established.get() == 1 {
-                    self.state.connected_peers.insert(peer_id);
-                    self.swarm
-                        .behaviour_mut()
-                        .gossipsub
-                        .add_explicit_peer(&peer_id);
-                    self.swarm
-                        .behaviour_mut()
-                        .kademlia
-                        .add_address(&peer_id, endpoint.get_remote_address().clone());
-                    self.state
-                        .emit(NetworkEvent::PeerConnected { peer_id })
-                        .await;
+                if let Some(info) = self.state.peers.get_mut(&peer_id) {
+                    info.connections = num_established.get() as usize;
+                    let address = endpoint.get_remote_address();
+                    if !info.addresses.contains(address) {
+                        info.addresses.push(address.clone());
+                    }
                 }
             }
 
             SwarmEvent::ConnectionClosed {
                 peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.state.peers.remove(&peer_id);
+                self.state.emit(NetworkEvent::PeerDisconnected { peer_id });
+            }
+
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                endpoint,
                 num_established,
                 ..
             } => {
-                if num_established == 0 {
-                    self.state.connected_peers.remove(&peer_id);
-                    self.state
-                        .emit(NetworkEvent::PeerDisconnected { peer_id })
-                        .await;
+                if let Some(info) = self.state.peers.get_mut(&peer_id) {
+                    info.connections = num_established as usize;
+                    let address = endpoint.get_remote_address();
+                    if info.addresses.len() > 1 {
+                        info.addresses.retain(|a| a != address);
+                    }
+                }
+            }
+
+            SwarmEvent::IncomingConnectionError {
+                peer_id,
+                error: ListenError::Denied { cause },
+                ..
+            } => {
+                if let Some(exceeded) = cause.downcast_ref::<Exceeded>() {
+                    self.connection_limit_reached(peer_id, ConnectionDirection::Listener, exceeded);
+                }
+            }
+
+            SwarmEvent::OutgoingConnectionError {
+                peer_id,
+                error: DialError::Denied { cause },
+                ..
+            } => {
+                if let Some(exceeded) = cause.downcast_ref::<Exceeded>() {
+                    self.connection_limit_reached(peer_id, ConnectionDirection::Dialer, exceeded);
                 }
             }
 
             SwarmEvent::NewListenAddr { address, .. } => {
-                self.state.emit(NetworkEvent::Listening { address }).await;
+                self.advertise_to_relay_clients(&address);
+                self.state.emit(NetworkEvent::Listening { address });
             }
 
             SwarmEvent::Behaviour(PeernetBehaviourEvent::Kademlia(event)) => {
-                KademliaHandler::handle(&mut self.state, event).await;
+                KademliaHandler::handle(&mut self.state, &mut self.swarm, event);
             }
 
             SwarmEvent::Behaviour(PeernetBehaviourEvent::Gossipsub(event)) => {
-                GossipsubHandler::handle(&mut self.state, event).await;
+                GossipsubHandler::handle(&mut self.state, event);
+            }
+
+            SwarmEvent::Behaviour(PeernetBehaviourEvent::Identify(event)) => {
+                IdentifyHandler::handle(&mut self.state, &mut self.swarm, *event);
+            }
+
+            SwarmEvent::Behaviour(PeernetBehaviourEvent::Ping(event)) => {
+                PingHandler::handle(&mut self.state, event);
+            }
+
+            SwarmEvent::Behaviour(PeernetBehaviourEvent::Autonat(event)) => {
+                AutonatHandler::handle(&mut self.state, &mut self.swarm, event);
+            }
+
+            SwarmEvent::Behaviour(PeernetBehaviourEvent::RelayClient(event)) => {
+                RelayHandler::handle_client(&mut self.state, event);
+            }
+
+            SwarmEvent::Behaviour(PeernetBehaviourEvent::RelayServer(event)) => {
+                RelayHandler::handle_server(&mut self.state, event);
             }
 
             SwarmEvent::Behaviour(PeernetBehaviourEvent::Mdns(event)) => {
-                MdnsHandler::handle(&mut self.state, &mut self.swarm, event).await;
+                MdnsHandler::handle(&mut self.state, &mut self.swarm, event);
             }
 
             _ => {}
         }
     }
+
+    fn connection_limit_reached(
+        &mut self,
+        peer_id: Option<PeerId>,
+        direction: ConnectionDirection,
+        exceeded: &Exceeded,
+    ) {
+        warn!(?peer_id, %direction, "{exceeded}");
+        self.state.emit(NetworkEvent::ConnectionLimitReached {
+            peer_id,
+            direction,
+            limit: exceeded.limit(),
+            reason: exceeded.to_string(),
+        });
+    }
 }
 
-async fn await_first_listen_addr(swarm: &mut libp2p::Swarm<PeernetBehaviour>) -> Multiaddr {
-    loop {
-        if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
-            return address;
+// The change is already in effect; the caller hears back once it is saved.
+fn reply_when_persisted(peer_id: PeerId, persisted: Persisted, reply: Reply<()>) {
+    tokio::spawn(async move {
+        let result = persisted.await.unwrap_or_else(|_| {
+            Err(PeernetError::Storage {
+                reason: "peer list writer stopped".into(),
+            })
+        });
+        let _ = reply.send(result.map_err(|e| peer_list_error(peer_id, e)));
+    });
+}
+
+fn peer_list_error(peer_id: PeerId, e: PeernetError) -> CommandError {
+    CommandError::PeerListFailed {
+        peer: peer_id.to_string(),
+        reason: e.to_string(),
+    }
+}
+
+fn kad_quorum(quorum: DhtQuorum) -> kad::Quorum {
+    match quorum {
+        DhtQuorum::One => kad::Quorum::One,
+        DhtQuorum::Majority => kad::Quorum::Majority,
+        DhtQuorum::All => kad::Quorum::All,
+        DhtQuorum::N(n) => kad::Quorum::N(n),
+    }
+}
+
+// The relay is the peer addressed just before `/p2p-circuit`.
+fn relay_of(addr: &Multiaddr) -> Option<PeerId> {
+    let mut relay = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::P2p(peer_id) => relay = Some(peer_id),
+            Protocol::P2pCircuit => return relay,
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn peer_id_of(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|p| match p {
+        Protocol::P2p(peer_id) => Some(peer_id),
+        _ => None,
+    })
+}
+
+// A wildcard listener reports one address per interface, one after another,
+// so after every listener has reported once we keep collecting until no new
+// address has arrived for LISTEN_SETTLE. Later ones become Listening events.
+// Anything else the swarm produces meanwhile is handed back for replay.
+async fn await_listen_addrs(
+    swarm: &mut libp2p::Swarm<PeernetBehaviour>,
+    listeners: Vec<ListenerId>,
+) -> (Vec<Multiaddr>, Vec<SwarmEvent<PeernetBehaviourEvent>>) {
+    let mut pending: HashSet<ListenerId> = listeners.into_iter().collect();
+    let mut addrs = Vec::new();
+    let mut backlog = Vec::new();
+    let mut settled_at = None;
+    while !pending.is_empty() || settled_at.is_some() {
+        let event = match settled_at {
+            Some(deadline) => {
+                match tokio::time::timeout_at(deadline, swarm.select_next_some()).await {
+                    Ok(event) => event,
+                    Err(_) => break,
+                }
+            }
+            None => swarm.select_next_some().await,
+        };
+        match event {
+            SwarmEvent::NewListenAddr {
+                listener_id,
+                address,
+            } => {
+                pending.remove(&listener_id);
+                addrs.push(address);
+                if pending.is_empty() {
+                    settled_at = Some(tokio::time::Instant::now() + LISTEN_SETTLE);
+                }
+            }
+            other => backlog.push(other),
         }
     }
+    (addrs, backlog)
 }
 
 async fn run_network_loop(
     config: NetworkConfig,
     mut command_rx: mpsc::Receiver<NetworkCommand>,
-    event_tx: mpsc::Sender<NetworkEvent>,
+    event_tx: broadcast::Sender<NetworkEvent>,
     cancel_token: CancellationToken,
 ) {
     let initial_topics = config.swarm.initial_topics.clone();
+    let replication =
+        NonZeroUsize::new(config.swarm.kademlia_replication).expect("replication > 0");
+
+    if let Err(e) = config.validate() {
+        warn!(%e, "invalid network config");
+        let _ = event_tx.send(NetworkEvent::ShutdownComplete);
+        return;
+    }
 
-    let (mut swarm, local_peer_id) = match build_swarm(config.swarm) {
+    let peer_lists = match PeerLists::open(&config.swarm.peer_lists) {
+        Ok(lists) => lists,
+        Err(e) => {
+            warn!(?e, "failed to open peer lists");
+            let _ = event_tx.send(NetworkEvent::ShutdownComplete);
+            return;
+        }
+    };
+
+    let mut registry = config.metrics_addr.map(|_| Registry::default());
+    let dht_mode = config.swarm.dht_mode;
+
+    let (mut swarm, local_peer_id) = match build_swarm(config.swarm, registry.as_mut()) {
         Ok(result) => result,
         Err(e) => {
             warn!(?e, "failed to build swarm");
-            let _ = event_tx.send(NetworkEvent::ShutdownComplete).await;
+            let _ = event_tx.send(NetworkEvent::ShutdownComplete);
             return;
         }
     };
 
-    let listen_addr: Multiaddr = format!("/ip4/0.0.0.0/tcp/{}", config.port)
-        .parse()
-        .expect("valid multiaddr");
+    let behaviour = swarm.behaviour_mut();
+    for peer_id in peer_lists.blocked() {
+        behaviour.blocked.block_peer(peer_id);
+        behaviour.gossipsub.blacklist_peer(&peer_id);
+    }
+    if let Some(allowed) = behaviour.allowed.as_mut() {
+        for peer_id in peer_lists.allowed() {
+            allowed.allow_peer(peer_id);
+        }
+    }
+
+    let metrics = registry
+        .as_mut()
+        .map(|registry| (Metrics::new(registry), PeernetMetrics::new(registry)));
 
-    if let Err(e) = swarm.listen_on(listen_addr.clone()) {
-        warn!(?e, "failed to listen");
-        let _ = event_tx.send(NetworkEvent::ShutdownComplete).await;
+    let metrics_token = cancel_token.child_token();
+    if let (Some(addr), Some(registry)) = (config.metrics_addr, registry) {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tokio::spawn(serve_metrics(
+                    listener,
+                    Arc::new(registry),
+                    metrics_token.clone(),
+                ));
+            }
+            Err(e) => {
+                warn!(%addr, ?e, "failed to bind metrics endpoint");
+                let _ = event_tx.send(NetworkEvent::ShutdownComplete);
+                return;
+            }
+        }
+    }
+
+    let listen_addrs = config.transport.listen_addrs(config.port);
+    if listen_addrs.is_empty() && config.relays.is_empty() {
+        warn!("no transport enabled to listen on");
+        let _ = event_tx.send(NetworkEvent::ShutdownComplete);
         return;
     }
 
-    let actual_addr = await_first_listen_addr(&mut swarm).await;
+    let mut listeners = Vec::new();
+    for addr in listen_addrs {
+        match swarm.listen_on(addr) {
+            Ok(id) => listeners.push(id),
+            Err(e) => {
+                warn!(?e, "failed to listen");
+                let _ = event_tx.send(NetworkEvent::ShutdownComplete);
+                return;
+            }
+        }
+    }
 
-    let state = NetworkState::new(local_peer_id, event_tx.clone());
-    let mut actor = NetworkActor { swarm, state };
+    let (actual_addrs, backlog) = await_listen_addrs(&mut swarm, listeners).await;
+
+    let (libp2p_metrics, peernet_metrics) = metrics.unzip();
+    let mut state = NetworkState::new(local_peer_id, peer_lists, event_tx.clone());
+    state.metrics = peernet_metrics;
+    state.dht_mode = dht_mode;
+    let mut actor = NetworkActor {
+        swarm,
+        state,
+        replication,
+        metrics: libp2p_metrics,
+    };
 
     for topic in initial_topics {
         let ident = gossipsub::IdentTopic::new(topic.as_ref());
@@ -300,17 +1098,47 @@ async fn run_network_loop(
         actor.state.add_subscription(&topic);
     }
 
-    let _ = event_tx
-        .send(NetworkEvent::Started {
-            local_peer_id,
-            listening_on: actual_addr,
-        })
-        .await;
+    for addr in &actual_addrs {
+        actor.advertise_to_relay_clients(addr);
+    }
+    let _ = event_tx.send(NetworkEvent::Started {
+        local_peer_id,
+        listening_on: actual_addrs,
+    });
+    for event in backlog {
+        actor.handle_swarm_event(event);
+    }
+
+    if !config.bootstrap_peers.is_empty() {
+        actor.bootstrap(&config.bootstrap_peers);
+    }
+    // Relayed addresses only appear once the relay accepts our reservation,
+    // so they are reported later as Listening events rather than in Started.
+    for relay in &config.relays {
+        let addr = relay.clone().with(Protocol::P2pCircuit);
+        if let Err(e) = actor.swarm.listen_on(addr) {
+            warn!(%relay, ?e, "failed to listen through relay");
+        }
+    }
+
+    let mut expiry = tokio::time::interval(config.record_expiry_interval);
+    let mut persist_failures = actor
+        .swarm
+        .behaviour_mut()
+        .kademlia
+        .store_mut()
+        .persist_failures();
 
     loop {
         tokio::select! {
             () = cancel_token.cancelled() => break,
 
+            _ = expiry.tick() => actor.expire_records(),
+
+            Some(reason) = persist_failures.recv() => {
+                actor.state.emit(NetworkEvent::RecordPersistFailed { reason });
+            }
+
             Some(cmd) = command_rx.recv() => {
                 match actor.handle_command(cmd) {
                     Ok(CommandOutcome::Shutdown) => break,
@@ -318,18 +1146,20 @@ async fn run_network_loop(
                     Err(e) => {
                         actor.state.emit(NetworkEvent::CommandFailed {
                             reason: e.to_string(),
-                        }).await;
+                        });
                     }
                 }
             }
 
             event = actor.swarm.select_next_some() => {
-                actor.handle_swarm_event(event).await;
+                actor.handle_swarm_event(event);
             }
         }
     }
 
-    let _ = event_tx.send(NetworkEvent::ShutdownComplete).await;
+    metrics_token.cancel();
+    drop(actor);
+    let _ = event_tx.send(NetworkEvent::ShutdownComplete);
 }
 
 #[cfg(test)]
@@ -349,8 +1179,17 @@ mod tests {
             .unwrap();
 
         match event {
-            NetworkEvent::Started { local_peer_id, .. } => {
+            NetworkEvent::Started {
+                local_peer_id,
+                listening_on,
+            } => {
                 assert!(local_peer_id.to_string().starts_with("12D3K"));
+                assert!(listening_on.iter().any(|a| a.to_string().contains("/tcp/")));
+                assert!(
+                    listening_on
+                        .iter()
+                        .any(|a| a.to_string().contains("/quic-v1"))
+                );
             }
             other => panic!("expected Started, got {other:?}"),
         }
@@ -358,6 +1197,82 @@ mod tests {
         cancel.cancel();
     }
 
+    #[test]
+    fn private_network_rejects_non_tcp_transports() {
+        let private = |transport| NetworkConfig {
+            transport,
+            swarm: SwarmConfig {
+                psk: Some(PreSharedKey::new([7; 32])),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tcp_only = TransportConfig {
+            quic: false,
+            ..Default::default()
+        };
+
+        assert!(private(tcp_only.clone()).validate().is_ok());
+        assert!(private(TransportConfig::default()).validate().is_err());
+        assert!(
+            private(TransportConfig {
+                memory: true,
+                ..tcp_only
+            })
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_request_is_reported_only_to_its_caller() {
+        let cancel = CancellationToken::new();
+        let mut handle = spawn(NetworkConfig::memory(), cancel.clone());
+        let _ = timeout(Duration::from_secs(5), handle.recv()).await;
+
+        let topic = TopicName::new("not-joined").unwrap();
+        let result = handle
+            .publish_to(topic, GossipPayload::from_text("hi").unwrap())
+            .await;
+        assert!(matches!(
+            result,
+            Err(PeernetError::Command(CommandError::PublishFailed { .. }))
+        ));
+
+        while let Ok(Some(event)) = timeout(Duration::from_millis(300), handle.recv()).await {
+            assert!(
+                !matches!(event, NetworkEvent::CommandFailed { .. }),
+                "failure was also broadcast"
+            );
+        }
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn cloned_handles_and_subscriptions_fan_out() {
+        let cancel = CancellationToken::new();
+        let mut handle = spawn(NetworkConfig::default(), cancel);
+
+        let _ = timeout(Duration::from_secs(5), handle.recv()).await;
+        let mut cloned = handle.clone();
+        let mut lifecycle = handle.subscribe_events(EventFilter::PeerLifecycle);
+        handle.shutdown().await.unwrap();
+
+        for events in [&mut handle.events, &mut cloned.events] {
+            loop {
+                match timeout(Duration::from_secs(5), events.recv()).await {
+                    Ok(Some(NetworkEvent::ShutdownComplete)) => break,
+                    Ok(Some(_)) => continue,
+                    Ok(None) => panic!("closed before ShutdownComplete"),
+                    Err(_) => panic!("timeout waiting for ShutdownComplete"),
+                }
+            }
+        }
+
+        let closed = timeout(Duration::from_secs(5), lifecycle.recv()).await;
+        assert!(matches!(closed, Ok(None)));
+    }
+
     #[tokio::test]
     async fn network_actor_shuts_down() {
         let cancel = CancellationToken::new();
@@ -0,0 +1,174 @@
+use peernet_core::{NetworkCommand, NetworkEvent};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    registry::Registry,
+};
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CommandLabels {
+    command: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeernetMetrics {
+    commands: Family<CommandLabels, Counter>,
+    command_failures: Counter,
+    pending_queries: Gauge,
+    event_channel_depth: Gauge,
+}
+
+impl PeernetMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let registry = registry.sub_registry_with_prefix("peernet");
+        let metrics = Self {
+            commands: Family::default(),
+            command_failures: Counter::default(),
+            pending_queries: Gauge::default(),
+            event_channel_depth: Gauge::default(),
+        };
+        registry.register(
+            "commands",
+            "Commands handled by the network actor",
+            metrics.commands.clone(),
+        );
+        registry.register(
+            "command_failures",
+            "CommandFailed events emitted",
+            metrics.command_failures.clone(),
+        );
+        registry.register(
+            "pending_queries",
+            "Kademlia queries awaiting a result",
+            metrics.pending_queries.clone(),
+        );
+        registry.register(
+            "event_channel_depth",
+            "Events buffered for the slowest subscriber",
+            metrics.event_channel_depth.clone(),
+        );
+        metrics
+    }
+
+    pub fn command_handled(&self, cmd: &NetworkCommand) {
+        let command = match cmd {
+            NetworkCommand::Shutdown => "shutdown",
+            NetworkCommand::Dial { .. } => "dial",
+            NetworkCommand::Publish { .. } => "publish",
+            NetworkCommand::Subscribe { .. } => "subscribe",
+            NetworkCommand::Unsubscribe { .. } => "unsubscribe",
+            NetworkCommand::ListSubscriptions { .. } => "list_subscriptions",
+            NetworkCommand::ListPeers { .. } => "list_peers",
+            NetworkCommand::ListPeerInfo { .. } => "list_peer_info",
+            NetworkCommand::GetLatency { .. } => "get_latency",
+            NetworkCommand::BlockPeer { .. } => "block_peer",
+            NetworkCommand::UnblockPeer { .. } => "unblock_peer",
+            NetworkCommand::AllowPeer { .. } => "allow_peer",
+            NetworkCommand::DisallowPeer { .. } => "disallow_peer",
+            NetworkCommand::ListBlockedPeers { .. } => "list_blocked_peers",
+            NetworkCommand::PutRecord { .. } => "put_record",
+            NetworkCommand::GetRecord { .. } => "get_record",
+            NetworkCommand::StartProviding { .. } => "start_providing",
+            NetworkCommand::GetProviders { .. } => "get_providers",
+            NetworkCommand::FindPeer { .. } => "find_peer",
+            NetworkCommand::GetClosestPeers { .. } => "get_closest_peers",
+        };
+        self.commands
+            .get_or_create(&CommandLabels { command })
+            .inc();
+    }
+
+    pub fn event_emitted(&self, event: &NetworkEvent, channel_depth: usize) {
+        if matches!(event, NetworkEvent::CommandFailed { .. }) {
+            self.command_failures.inc();
+        }
+        self.event_channel_depth.set(channel_depth as i64);
+    }
+
+    pub fn set_pending_queries(&self, count: usize) {
+        self.pending_queries.set(count as i64);
+    }
+}
+
+pub async fn serve_metrics(
+    listener: TcpListener,
+    registry: Arc<Registry>,
+    cancel_token: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            () = cancel_token.cancelled() => break,
+
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(respond(stream, registry.clone()));
+                }
+                Err(e) => warn!(?e, "failed to accept metrics connection"),
+            },
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, registry: Arc<Registry>) {
+    let mut buf = [0u8; 1024];
+    let Ok(n) = stream.read(&mut buf).await else {
+        return;
+    };
+
+    let mut body = String::new();
+    let response = if !buf[..n].starts_with(b"GET /metrics ") {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    } else if encode(&mut body, &registry).is_err() {
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string()
+    } else {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    };
+
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peernet_core::PeerId;
+
+    #[test]
+    fn counts_commands_and_failures() {
+        let mut registry = Registry::default();
+        let metrics = PeernetMetrics::new(&mut registry);
+
+        metrics.command_handled(&NetworkCommand::Shutdown);
+        metrics.event_emitted(
+            &NetworkEvent::CommandFailed {
+                reason: "boom".into(),
+            },
+            3,
+        );
+        metrics.event_emitted(
+            &NetworkEvent::PeerConnected {
+                peer_id: PeerId::random(),
+            },
+            4,
+        );
+
+        let mut out = String::new();
+        encode(&mut out, &registry).unwrap();
+        assert!(out.contains("peernet_commands_total{command=\"shutdown\"} 1"));
+        assert!(out.contains("peernet_command_failures_total 1"));
+        assert!(out.contains("peernet_event_channel_depth 4"));
+    }
+}
@@ -0,0 +1,267 @@
+use libp2p::PeerId;
+use peernet_core::{PeernetError, PeernetResult};
+use redb::{Database, ReadableTable, TableDefinition};
+use std::{collections::BTreeSet, path::PathBuf, sync::mpsc as std_mpsc, thread::JoinHandle};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+const BLOCKED: TableDefinition<&[u8], ()> = TableDefinition::new("blocked");
+const ALLOWED: TableDefinition<&[u8], ()> = TableDefinition::new("allowed");
+const DATABASE_FILE: &str = "peers.redb";
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerListsConfig {
+    // Without a data directory the lists last only as long as the node.
+    pub data_dir: Option<PathBuf>,
+    pub only_allowed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum List {
+    Blocked,
+    Allowed,
+}
+
+impl List {
+    fn table(self) -> TableDefinition<'static, &'static [u8], ()> {
+        match self {
+            List::Blocked => BLOCKED,
+            List::Allowed => ALLOWED,
+        }
+    }
+}
+
+// Resolves once a change is on disk, or straight away without a data directory.
+pub(crate) type Persisted = oneshot::Receiver<PeernetResult<()>>;
+
+// The swarm's allow/block behaviours enforce the lists; this keeps the
+// authoritative copy. Changes apply immediately and are written through to
+// disk on a separate thread, so a failed write only costs the change its
+// persistence.
+pub struct PeerLists {
+    blocked: BTreeSet<PeerId>,
+    allowed: BTreeSet<PeerId>,
+    only_allowed: bool,
+    writer: Option<ListWriter>,
+}
+
+impl PeerLists {
+    pub fn open(config: &PeerListsConfig) -> PeernetResult<Self> {
+        let mut lists = Self {
+            blocked: BTreeSet::new(),
+            allowed: BTreeSet::new(),
+            only_allowed: config.only_allowed,
+            writer: None,
+        };
+        if let Some(data_dir) = &config.data_dir {
+            std::fs::create_dir_all(data_dir).map_err(storage_error)?;
+            let db = Database::create(data_dir.join(DATABASE_FILE)).map_err(storage_error)?;
+            lists.blocked = load(&db, List::Blocked).map_err(storage_error)?;
+            lists.allowed = load(&db, List::Allowed).map_err(storage_error)?;
+            lists.writer = Some(ListWriter::spawn(db).map_err(storage_error)?);
+        }
+        Ok(lists)
+    }
+
+    pub fn blocked(&self) -> impl Iterator<Item = PeerId> + '_ {
+        self.blocked.iter().copied()
+    }
+
+    pub fn allowed(&self) -> impl Iterator<Item = PeerId> + '_ {
+        self.allowed.iter().copied()
+    }
+
+    // Whether we should try to connect to a peer at all.
+    pub fn permits(&self, peer_id: &PeerId) -> bool {
+        !self.blocked.contains(peer_id) && (!self.only_allowed || self.allowed.contains(peer_id))
+    }
+
+    pub fn block(&mut self, peer_id: PeerId) -> Persisted {
+        self.blocked.insert(peer_id);
+        self.persist(List::Blocked, peer_id, true)
+    }
+
+    pub fn unblock(&mut self, peer_id: PeerId) -> Persisted {
+        self.blocked.remove(&peer_id);
+        self.persist(List::Blocked, peer_id, false)
+    }
+
+    pub fn allow(&mut self, peer_id: PeerId) -> Persisted {
+        self.allowed.insert(peer_id);
+        self.persist(List::Allowed, peer_id, true)
+    }
+
+    pub fn disallow(&mut self, peer_id: PeerId) -> Persisted {
+        self.allowed.remove(&peer_id);
+        self.persist(List::Allowed, peer_id, false)
+    }
+
+    fn persist(&self, list: List, peer_id: PeerId, present: bool) -> Persisted {
+        let (done, persisted) = oneshot::channel();
+        match &self.writer {
+            Some(writer) => writer.send(ListOp {
+                list,
+                peer_id,
+                present,
+                done,
+            }),
+            None => {
+                let _ = done.send(Ok(()));
+            }
+        }
+        persisted
+    }
+}
+
+struct ListOp {
+    list: List,
+    peer_id: PeerId,
+    present: bool,
+    done: oneshot::Sender<PeernetResult<()>>,
+}
+
+struct ListWriter {
+    ops: Option<std_mpsc::Sender<ListOp>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ListWriter {
+    fn spawn(db: Database) -> std::io::Result<Self> {
+        let (ops, rx) = std_mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("peernet-peers".into())
+            .spawn(move || write_loop(&db, &rx))?;
+        Ok(Self {
+            ops: Some(ops),
+            thread: Some(thread),
+        })
+    }
+
+    fn send(&self, op: ListOp) {
+        if let Some(ops) = &self.ops {
+            let _ = ops.send(op);
+        }
+    }
+}
+
+// Like the record store's writer: queued changes are drained before the
+// thread exits, so the lists are on disk once they are dropped.
+impl Drop for ListWriter {
+    fn drop(&mut self) {
+        self.ops.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn write_loop(db: &Database, ops: &std_mpsc::Receiver<ListOp>) {
+    while let Ok(op) = ops.recv() {
+        let batch: Vec<ListOp> = std::iter::once(op).chain(ops.try_iter()).collect();
+        let result = write_batch(db, &batch).map_err(|e| {
+            warn!(%e, "failed to persist peer list changes");
+            e.to_string()
+        });
+        for op in batch {
+            let _ = op.done.send(result.clone().map_err(storage_error));
+        }
+    }
+}
+
+fn write_batch(db: &Database, batch: &[ListOp]) -> Result<(), redb::Error> {
+    let txn = db.begin_write()?;
+    {
+        let mut blocked = txn.open_table(BLOCKED)?;
+        let mut allowed = txn.open_table(ALLOWED)?;
+        for op in batch {
+            let table = match op.list {
+                List::Blocked => &mut blocked,
+                List::Allowed => &mut allowed,
+            };
+            let key = op.peer_id.to_bytes();
+            if op.present {
+                table.insert(key.as_slice(), ())?;
+            } else {
+                table.remove(key.as_slice())?;
+            }
+        }
+    }
+    txn.commit()?;
+    Ok(())
+}
+
+fn load(db: &Database, list: List) -> Result<BTreeSet<PeerId>, redb::Error> {
+    let txn = db.begin_write()?;
+    let mut peers = BTreeSet::new();
+    {
+        let table = txn.open_table(list.table())?;
+        for entry in table.iter()? {
+            let (key, _) = entry?;
+            match PeerId::from_bytes(key.value()) {
+                Ok(peer_id) => {
+                    peers.insert(peer_id);
+                }
+                Err(e) => warn!(%e, ?list, "skipping corrupt peer list entry"),
+            }
+        }
+    }
+    txn.commit()?;
+    Ok(peers)
+}
+
+fn storage_error(e: impl ToString) -> PeernetError {
+    PeernetError::Storage {
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(persisted: Persisted) {
+        persisted.blocking_recv().unwrap().unwrap();
+    }
+
+    #[test]
+    fn lists_survive_reopening() {
+        let data_dir = std::env::temp_dir().join(format!("peernet-peers-{}", PeerId::random()));
+        let config = PeerListsConfig {
+            data_dir: Some(data_dir.clone()),
+            only_allowed: false,
+        };
+        let [banned, unbanned, friend, former_friend] = std::array::from_fn(|_| PeerId::random());
+
+        {
+            let mut lists = PeerLists::open(&config).unwrap();
+            saved(lists.block(banned));
+            saved(lists.block(unbanned));
+            saved(lists.unblock(unbanned));
+            saved(lists.allow(friend));
+            saved(lists.allow(former_friend));
+            saved(lists.disallow(former_friend));
+        }
+
+        let lists = PeerLists::open(&config).unwrap();
+        assert_eq!(lists.blocked().collect::<Vec<_>>(), vec![banned]);
+        assert_eq!(lists.allowed().collect::<Vec<_>>(), vec![friend]);
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn only_allowed_permits_listed_peers() {
+        let mut lists = PeerLists::open(&PeerListsConfig {
+            data_dir: None,
+            only_allowed: true,
+        })
+        .unwrap();
+        let (friend, stranger) = (PeerId::random(), PeerId::random());
+        saved(lists.allow(friend));
+
+        assert!(lists.permits(&friend));
+        assert!(!lists.permits(&stranger));
+
+        saved(lists.block(friend));
+        assert!(!lists.permits(&friend));
+    }
+}
@@ -1,7 +1,12 @@
+use crate::metrics::PeernetMetrics;
+use crate::peer_lists::PeerLists;
+use libp2p::Multiaddr;
 use libp2p::{gossipsub, kad};
-use peernet_core::{DhtKey, NetworkEvent, PeerId, TopicName};
-use std::collections::{HashMap, HashSet};
-use tokio::sync::mpsc;
+use peernet_core::{
+    DhtKey, DhtLookup, DhtMode, DhtValue, NetworkEvent, PeerId, PeerInfo, Reply, TopicName,
+};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
 
 fn topic_hash(topic: &TopicName) -> gossipsub::TopicHash {
     gossipsub::IdentTopic::new(topic.as_ref()).hash()
@@ -9,43 +14,79 @@ fn topic_hash(topic: &TopicName) -> gossipsub::TopicHash {
 
 #[derive(Debug)]
 pub enum PendingQuery {
-    GetRecord(DhtKey),
-    PutRecord(DhtKey),
-    GetProviders(DhtKey),
-    StartProviding(DhtKey),
+    GetRecord {
+        key: DhtKey,
+        required: usize,
+        values: Vec<DhtValue>,
+        local: Option<DhtValue>,
+        reply: Reply<DhtLookup>,
+    },
+    PutRecord {
+        key: DhtKey,
+        reply: Reply<()>,
+    },
+    GetProviders {
+        key: DhtKey,
+        reply: Reply<Vec<PeerId>>,
+    },
+    FindPeer {
+        peer_id: PeerId,
+        reply: Reply<Vec<Multiaddr>>,
+    },
+    GetClosestPeers {
+        key: DhtKey,
+        reply: Reply<Vec<PeerId>>,
+    },
+    StartProviding {
+        key: DhtKey,
+        reply: Reply<()>,
+    },
 }
 
 pub struct NetworkState {
     pub local_peer_id: PeerId,
-    pub connected_peers: HashSet<PeerId>,
-    pub subscribed_topics: HashSet<gossipsub::TopicHash>,
+    pub peers: HashMap<PeerId, PeerInfo>,
+    pub peer_lists: PeerLists,
+    pub subscribed_topics: HashMap<gossipsub::TopicHash, TopicName>,
     pub pending_queries: HashMap<kad::QueryId, PendingQuery>,
-    pub event_tx: mpsc::Sender<NetworkEvent>,
+    pub event_tx: broadcast::Sender<NetworkEvent>,
+    pub metrics: Option<PeernetMetrics>,
+    pub dht_mode: Option<DhtMode>,
 }
 
 impl NetworkState {
-    pub fn new(local_peer_id: PeerId, event_tx: mpsc::Sender<NetworkEvent>) -> Self {
+    pub fn new(
+        local_peer_id: PeerId,
+        peer_lists: PeerLists,
+        event_tx: broadcast::Sender<NetworkEvent>,
+    ) -> Self {
         Self {
             local_peer_id,
-            connected_peers: HashSet::new(),
-            subscribed_topics: HashSet::new(),
+            peers: HashMap::new(),
+            peer_lists,
+            subscribed_topics: HashMap::new(),
             pending_queries: HashMap::new(),
             event_tx,
+            metrics: None,
+            dht_mode: None,
         }
     }
 
-    pub async fn emit(&self, event: NetworkEvent) {
-        let _ = self.event_tx.send(event).await;
+    pub fn emit(&self, event: NetworkEvent) {
+        if let Some(metrics) = &self.metrics {
+            metrics.event_emitted(&event, self.event_tx.len());
+        }
+        let _ = self.event_tx.send(event);
     }
 
     pub fn is_subscribed(&self, topic: &TopicName) -> bool {
         let hash = topic_hash(topic);
-        self.subscribed_topics.contains(&hash)
+        self.subscribed_topics.contains_key(&hash)
     }
 
     pub fn add_subscription(&mut self, topic: &TopicName) {
         let hash = topic_hash(topic);
-        self.subscribed_topics.insert(hash);
+        self.subscribed_topics.insert(hash, topic.clone());
     }
 
     pub fn remove_subscription(&mut self, topic: &TopicName) {
@@ -53,11 +94,30 @@ impl NetworkState {
         self.subscribed_topics.remove(&hash);
     }
 
+    pub fn subscriptions(&self) -> Vec<TopicName> {
+        let mut topics: Vec<TopicName> = self.subscribed_topics.values().cloned().collect();
+        topics.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        topics
+    }
+
     pub fn track_query(&mut self, id: kad::QueryId, query: PendingQuery) {
         self.pending_queries.insert(id, query);
+        self.record_pending_queries();
+    }
+
+    pub fn query_mut(&mut self, id: &kad::QueryId) -> Option<&mut PendingQuery> {
+        self.pending_queries.get_mut(id)
     }
 
     pub fn complete_query(&mut self, id: &kad::QueryId) -> Option<PendingQuery> {
-        self.pending_queries.remove(id)
+        let query = self.pending_queries.remove(id);
+        self.record_pending_queries();
+        query
+    }
+
+    fn record_pending_queries(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.set_pending_queries(self.pending_queries.len());
+        }
     }
 }
@@ -0,0 +1,547 @@
+use libp2p::{
+    Multiaddr, PeerId,
+    kad::{
+        ProviderRecord, Record, RecordKey,
+        store::{self, MemoryStore, MemoryStoreConfig, RecordStore},
+    },
+};
+use peernet_core::{PeernetError, PeernetResult};
+use redb::{Database, ReadableTable, TableDefinition};
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    sync::mpsc as std_mpsc,
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+const RECORDS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("records");
+const PROVIDERS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("providers");
+const DATABASE_FILE: &str = "records.redb";
+
+#[derive(Debug, Clone, Default)]
+pub enum RecordStoreBackend {
+    #[default]
+    Memory,
+    Disk {
+        data_dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordStoreConfig {
+    pub backend: RecordStoreBackend,
+    pub max_records: usize,
+    pub max_value_bytes: usize,
+    pub max_providers_per_key: usize,
+    pub max_provided_keys: usize,
+}
+
+impl Default for RecordStoreConfig {
+    fn default() -> Self {
+        let limits = MemoryStoreConfig::default();
+        Self {
+            backend: RecordStoreBackend::Memory,
+            max_records: limits.max_records,
+            max_value_bytes: limits.max_value_bytes,
+            max_providers_per_key: limits.max_providers_per_key,
+            max_provided_keys: limits.max_provided_keys,
+        }
+    }
+}
+
+impl RecordStoreConfig {
+    fn limits(&self) -> MemoryStoreConfig {
+        MemoryStoreConfig {
+            max_records: self.max_records,
+            max_value_bytes: self.max_value_bytes,
+            max_providers_per_key: self.max_providers_per_key,
+            max_provided_keys: self.max_provided_keys,
+        }
+    }
+}
+
+// Records are served from memory and written through to disk, so the
+// iterator types and limit checks are exactly those of `MemoryStore`.
+// Disk writes happen on a dedicated thread so a burst of inbound
+// PUT_VALUEs never waits on fsync inside the network loop.
+pub struct PeernetStore {
+    memory: MemoryStore,
+    writer: Option<StoreWriter>,
+    failures: Option<mpsc::UnboundedReceiver<String>>,
+}
+
+impl PeernetStore {
+    pub fn open(local_id: PeerId, config: &RecordStoreConfig) -> PeernetResult<Self> {
+        let mut memory = MemoryStore::with_config(local_id, config.limits());
+        match &config.backend {
+            RecordStoreBackend::Memory => Ok(Self {
+                memory,
+                writer: None,
+                failures: None,
+            }),
+            RecordStoreBackend::Disk { data_dir } => {
+                std::fs::create_dir_all(data_dir).map_err(storage_error)?;
+                let db = Database::create(data_dir.join(DATABASE_FILE)).map_err(storage_error)?;
+                load(&db, &mut memory).map_err(storage_error)?;
+                let (failures_tx, failures) = mpsc::unbounded_channel();
+                let writer = StoreWriter::spawn(db, failures_tx).map_err(storage_error)?;
+                Ok(Self {
+                    memory,
+                    writer: Some(writer),
+                    failures: Some(failures),
+                })
+            }
+        }
+    }
+
+    // Reasons for failed disk writes, for the network loop to report. The
+    // in-memory backend never fails, so its receiver simply closes.
+    pub(crate) fn persist_failures(&mut self) -> mpsc::UnboundedReceiver<String> {
+        self.failures
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1)
+    }
+
+    fn persist(&self, op: StoreOp) {
+        if let Some(writer) = &self.writer {
+            writer.send(op);
+        }
+    }
+}
+
+fn load(db: &Database, memory: &mut MemoryStore) -> Result<(), redb::Error> {
+    let txn = db.begin_write()?;
+    {
+        let mut records = txn.open_table(RECORDS)?;
+        let mut expired = Vec::new();
+        for entry in records.iter()? {
+            let (key, value) = entry?;
+            match decode_record(key.value(), value.value()) {
+                Some(record) => {
+                    if let Err(e) = memory.put(record) {
+                        warn!(%e, "dropping persisted record over limit");
+                        expired.push(key.value().to_vec());
+                    }
+                }
+                None => expired.push(key.value().to_vec()),
+            }
+        }
+        for key in expired {
+            records.remove(key.as_slice())?;
+        }
+
+        let mut providers = txn.open_table(PROVIDERS)?;
+        let mut expired = Vec::new();
+        for entry in providers.iter()? {
+            let (key, value) = entry?;
+            match decode_provider(value.value()) {
+                Some(record) => {
+                    if let Err(e) = memory.add_provider(record) {
+                        warn!(%e, "dropping persisted provider record over limit");
+                        expired.push(key.value().to_vec());
+                    }
+                }
+                None => expired.push(key.value().to_vec()),
+            }
+        }
+        for key in expired {
+            providers.remove(key.as_slice())?;
+        }
+    }
+    txn.commit()?;
+    Ok(())
+}
+
+enum StoreOp {
+    PutRecord { key: Vec<u8>, value: Vec<u8> },
+    RemoveRecord { key: Vec<u8> },
+    AddProvider { key: Vec<u8>, value: Vec<u8> },
+    RemoveProvider { key: Vec<u8> },
+}
+
+struct StoreWriter {
+    ops: Option<std_mpsc::Sender<StoreOp>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StoreWriter {
+    fn spawn(db: Database, failures: mpsc::UnboundedSender<String>) -> std::io::Result<Self> {
+        let (ops, rx) = std_mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("peernet-store".into())
+            .spawn(move || write_loop(&db, &rx, &failures))?;
+        Ok(Self {
+            ops: Some(ops),
+            thread: Some(thread),
+        })
+    }
+
+    fn send(&self, op: StoreOp) {
+        if let Some(ops) = &self.ops {
+            let _ = ops.send(op);
+        }
+    }
+}
+
+// Closing the channel lets the writer drain what is queued, so everything
+// accepted before shutdown is on disk once the store is dropped.
+impl Drop for StoreWriter {
+    fn drop(&mut self) {
+        self.ops.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Everything queued while the previous commit was syncing goes into the
+// next transaction, so one fsync covers a whole burst of changes.
+fn write_loop(
+    db: &Database,
+    ops: &std_mpsc::Receiver<StoreOp>,
+    failures: &mpsc::UnboundedSender<String>,
+) {
+    while let Ok(op) = ops.recv() {
+        let batch: Vec<StoreOp> = std::iter::once(op).chain(ops.try_iter()).collect();
+        if let Err(e) = write_batch(db, batch) {
+            warn!(%e, "failed to persist record store changes");
+            let _ = failures.send(e.to_string());
+        }
+    }
+}
+
+fn write_batch(db: &Database, batch: Vec<StoreOp>) -> Result<(), redb::Error> {
+    let txn = db.begin_write()?;
+    {
+        let mut records = txn.open_table(RECORDS)?;
+        let mut providers = txn.open_table(PROVIDERS)?;
+        for op in batch {
+            match op {
+                StoreOp::PutRecord { key, value } => {
+                    records.insert(key.as_slice(), value.as_slice())?;
+                }
+                StoreOp::RemoveRecord { key } => {
+                    records.remove(key.as_slice())?;
+                }
+                StoreOp::AddProvider { key, value } => {
+                    providers.insert(key.as_slice(), value.as_slice())?;
+                }
+                StoreOp::RemoveProvider { key } => {
+                    providers.remove(key.as_slice())?;
+                }
+            }
+        }
+    }
+    txn.commit()?;
+    Ok(())
+}
+
+impl RecordStore for PeernetStore {
+    type RecordsIter<'a> = <MemoryStore as RecordStore>::RecordsIter<'a>;
+    type ProvidedIter<'a> = <MemoryStore as RecordStore>::ProvidedIter<'a>;
+
+    fn get(&self, k: &RecordKey) -> Option<Cow<'_, Record>> {
+        self.memory.get(k)
+    }
+
+    fn put(&mut self, r: Record) -> store::Result<()> {
+        let value = encode_record(&r);
+        let key = r.key.to_vec();
+        self.memory.put(r)?;
+        self.persist(StoreOp::PutRecord { key, value });
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &RecordKey) {
+        self.memory.remove(k);
+        self.persist(StoreOp::RemoveRecord { key: k.to_vec() });
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        self.memory.records()
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> store::Result<()> {
+        let value = encode_provider(&record);
+        let key = provider_key(&record.key, &record.provider);
+        self.memory.add_provider(record)?;
+        self.persist(StoreOp::AddProvider { key, value });
+        Ok(())
+    }
+
+    fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+        self.memory.providers(key)
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        self.memory.provided()
+    }
+
+    fn remove_provider(&mut self, k: &RecordKey, p: &PeerId) {
+        self.memory.remove_provider(k, p);
+        self.persist(StoreOp::RemoveProvider {
+            key: provider_key(k, p),
+        });
+    }
+}
+
+fn storage_error(e: impl ToString) -> PeernetError {
+    PeernetError::Storage {
+        reason: e.to_string(),
+    }
+}
+
+fn provider_key(key: &RecordKey, provider: &PeerId) -> Vec<u8> {
+    let mut out = key.to_vec();
+    out.extend_from_slice(&provider.to_bytes());
+    out
+}
+
+fn to_wall_clock(at: Instant) -> u64 {
+    let remaining = at.saturating_duration_since(Instant::now());
+    (SystemTime::now() + remaining)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn from_wall_clock(millis: u64) -> Option<Instant> {
+    let at = UNIX_EPOCH + Duration::from_millis(millis);
+    let remaining = at.duration_since(SystemTime::now()).ok()?;
+    Some(Instant::now() + remaining)
+}
+
+fn encode_record(record: &Record) -> Vec<u8> {
+    let mut out = Encoder::default();
+    out.optional_bytes(record.publisher.map(|p| p.to_bytes()));
+    out.optional_u64(record.expires.map(to_wall_clock));
+    out.bytes(&record.value);
+    out.0
+}
+
+fn decode_record(key: &[u8], bytes: &[u8]) -> Option<Record> {
+    let mut input = Decoder(bytes);
+    let publisher = match input.optional_bytes()? {
+        Some(b) => Some(PeerId::from_bytes(b).ok()?),
+        None => None,
+    };
+    let expires = match input.optional_u64()? {
+        Some(millis) => Some(from_wall_clock(millis)?),
+        None => None,
+    };
+    let value = input.bytes()?.to_vec();
+    Some(Record {
+        key: RecordKey::new(&key),
+        value,
+        publisher,
+        expires,
+    })
+}
+
+fn encode_provider(record: &ProviderRecord) -> Vec<u8> {
+    let mut out = Encoder::default();
+    out.bytes(&record.key.to_vec());
+    out.bytes(&record.provider.to_bytes());
+    out.optional_u64(record.expires.map(to_wall_clock));
+    out.u64(record.addresses.len() as u64);
+    for addr in &record.addresses {
+        out.bytes(&addr.to_vec());
+    }
+    out.0
+}
+
+fn decode_provider(bytes: &[u8]) -> Option<ProviderRecord> {
+    let mut input = Decoder(bytes);
+    let key = RecordKey::new(&input.bytes()?);
+    let provider = PeerId::from_bytes(input.bytes()?).ok()?;
+    let expires = match input.optional_u64()? {
+        Some(millis) => Some(from_wall_clock(millis)?),
+        None => None,
+    };
+    let count = input.u64()?;
+    let mut addresses = Vec::new();
+    for _ in 0..count {
+        addresses.push(Multiaddr::try_from(input.bytes()?.to_vec()).ok()?);
+    }
+    Some(ProviderRecord {
+        key,
+        provider,
+        expires,
+        addresses,
+    })
+}
+
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.u64(b.len() as u64);
+        self.0.extend_from_slice(b);
+    }
+
+    fn optional_u64(&mut self, v: Option<u64>) {
+        match v {
+            Some(v) => {
+                self.0.push(1);
+                self.u64(v);
+            }
+            None => self.0.push(0),
+        }
+    }
+
+    fn optional_bytes(&mut self, b: Option<Vec<u8>>) {
+        match b {
+            Some(b) => {
+                self.0.push(1);
+                self.bytes(&b);
+            }
+            None => self.0.push(0),
+        }
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        self.take(len)
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        match self.take(1)? {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+
+    fn optional_u64(&mut self) -> Option<Option<u64>> {
+        if self.flag()? {
+            Some(Some(self.u64()?))
+        } else {
+            Some(None)
+        }
+    }
+
+    fn optional_bytes(&mut self) -> Option<Option<&'a [u8]>> {
+        if self.flag()? {
+            Some(Some(self.bytes()?))
+        } else {
+            Some(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_config(max_records: usize) -> (RecordStoreConfig, PathBuf) {
+        let data_dir = std::env::temp_dir().join(format!("peernet-store-{}", PeerId::random()));
+        let config = RecordStoreConfig {
+            backend: RecordStoreBackend::Disk {
+                data_dir: data_dir.clone(),
+            },
+            max_records,
+            ..Default::default()
+        };
+        (config, data_dir)
+    }
+
+    fn record(key: &str, expires: Option<Instant>) -> Record {
+        Record {
+            key: RecordKey::new(&key),
+            value: key.as_bytes().to_vec(),
+            publisher: Some(PeerId::random()),
+            expires,
+        }
+    }
+
+    #[test]
+    fn records_and_providers_survive_reopen() {
+        let local_id = PeerId::random();
+        let (config, data_dir) = disk_config(16);
+        let key = RecordKey::new(&"provided");
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        {
+            let mut store = PeernetStore::open(local_id, &config).unwrap();
+            store.put(record("kept", None)).unwrap();
+            store
+                .add_provider(ProviderRecord::new(
+                    key.clone(),
+                    local_id,
+                    vec![addr.clone()],
+                ))
+                .unwrap();
+        }
+
+        let store = PeernetStore::open(local_id, &config).unwrap();
+        let kept = store.get(&RecordKey::new(&"kept")).unwrap();
+        assert_eq!(kept.value, b"kept");
+        assert_eq!(store.provided().count(), 1);
+        assert_eq!(store.providers(&key)[0].addresses, vec![addr]);
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn removed_and_expired_records_are_not_reloaded() {
+        let local_id = PeerId::random();
+        let (config, data_dir) = disk_config(16);
+
+        {
+            let mut store = PeernetStore::open(local_id, &config).unwrap();
+            store.put(record("removed", None)).unwrap();
+            store.remove(&RecordKey::new(&"removed"));
+            store
+                .put(record(
+                    "expiring",
+                    Some(Instant::now() + Duration::from_millis(10)),
+                ))
+                .unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+
+        let store = PeernetStore::open(local_id, &config).unwrap();
+        assert_eq!(store.records().count(), 0);
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn enforces_record_limit() {
+        let (config, data_dir) = disk_config(1);
+        let mut store = PeernetStore::open(PeerId::random(), &config).unwrap();
+
+        store.put(record("first", None)).unwrap();
+        assert!(matches!(
+            store.put(record("second", None)),
+            Err(store::Error::MaxRecords)
+        ));
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+}
@@ -1,107 +1,391 @@
-use crate::behaviour::PeernetBehaviour;
+use crate::{
+    behaviour::PeernetBehaviour,
+    peer_lists::PeerListsConfig,
+    store::{PeernetStore, RecordStoreConfig},
+};
 use libp2p::{
-    PeerId, StreamProtocol, SwarmBuilder,
+    PeerId, StreamProtocol, SwarmBuilder, Transport, allow_block_list, autonat,
+    connection_limits::{self, ConnectionLimits},
+    core::{
+        muxing::StreamMuxerBox,
+        transport::{Boxed, MemoryTransport},
+        upgrade,
+    },
     gossipsub::{self, MessageAuthenticity, ValidationMode},
+    identify,
     identity::Keypair,
-    kad::{self, Mode, store::MemoryStore},
-    mdns,
+    kad::{self, Mode},
+    mdns, noise, ping,
+    pnet::{PnetConfig, PreSharedKey},
+    relay,
     swarm::Swarm,
+    tcp, websocket, yamux,
 };
-use peernet_core::{PeernetError, PeernetResult, TopicName};
+use peernet_core::{DhtMode, PeernetError, PeernetResult, TopicName};
+use prometheus_client::registry::Registry;
 use std::time::Duration;
+use tracing::info;
 
 pub const DEFAULT_TOPIC: &str = "peernet-global";
-const KADEMLIA_PROTOCOL: &str = "/peernet/kad/1.0.0";
+pub const KADEMLIA_PROTOCOL: &str = "/peernet/kad/1.0.0";
+const IDENTIFY_PROTOCOL_VERSION: &str = "/peernet/1.0.0";
 
 #[derive(Debug, Clone)]
 pub struct SwarmConfig {
     pub keypair: Option<Keypair>,
+    pub psk: Option<PreSharedKey>,
     pub mdns_query_interval: Duration,
     pub gossipsub_heartbeat: Duration,
     pub initial_topics: Vec<TopicName>,
     pub kademlia_replication: usize,
+    pub kademlia_bootstrap_interval: Duration,
+    // `None` follows AutoNAT: a node found to be private drops to client mode.
+    pub dht_mode: Option<DhtMode>,
+    pub record_ttl: Option<Duration>,
+    pub record_replication_interval: Option<Duration>,
+    pub record_publication_interval: Option<Duration>,
+    pub provider_record_ttl: Option<Duration>,
+    pub provider_publication_interval: Option<Duration>,
+    pub enable_mdns: bool,
+    pub agent_version: String,
+    pub ping_interval: Duration,
+    pub connection_limits: ConnectionLimitsConfig,
+    pub autonat: AutonatConfig,
+    pub relay_server: RelayServerConfig,
+    pub peer_lists: PeerListsConfig,
+    pub record_store: RecordStoreConfig,
+}
+
+// `None` leaves that kind of connection unlimited.
+// Every limit is off unless the operator sets one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionLimitsConfig {
+    pub max_pending_incoming: Option<u32>,
+    pub max_pending_outgoing: Option<u32>,
+    pub max_established_incoming: Option<u32>,
+    pub max_established_outgoing: Option<u32>,
+    pub max_established_per_peer: Option<u32>,
+}
+
+// How soon and how often connected peers are asked to dial us back.
+// `only_global_ips` ignores probes between peers on private addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutonatConfig {
+    pub boot_delay: Duration,
+    pub retry_interval: Duration,
+    pub refresh_interval: Duration,
+    pub only_global_ips: bool,
+}
+
+impl Default for AutonatConfig {
+    fn default() -> Self {
+        let defaults = autonat::Config::default();
+        Self {
+            boot_delay: defaults.boot_delay,
+            retry_interval: defaults.retry_interval,
+            refresh_interval: defaults.refresh_interval,
+            only_global_ips: defaults.only_global_ips,
+        }
+    }
+}
+
+impl From<AutonatConfig> for autonat::Config {
+    fn from(config: AutonatConfig) -> Self {
+        autonat::Config {
+            boot_delay: config.boot_delay,
+            retry_interval: config.retry_interval,
+            refresh_interval: config.refresh_interval,
+            only_global_ips: config.only_global_ips,
+            ..Default::default()
+        }
+    }
+}
+
+// Limits on the circuits we relay for others when `enabled`; every node can
+// use relays as a client regardless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayServerConfig {
+    pub enabled: bool,
+    pub max_reservations: usize,
+    pub max_reservations_per_peer: usize,
+    pub reservation_duration: Duration,
+    pub max_circuits: usize,
+    pub max_circuits_per_peer: usize,
+    pub max_circuit_duration: Duration,
+    pub max_circuit_bytes: u64,
+}
+
+impl Default for RelayServerConfig {
+    fn default() -> Self {
+        let defaults = relay::Config::default();
+        Self {
+            enabled: false,
+            max_reservations: defaults.max_reservations,
+            max_reservations_per_peer: defaults.max_reservations_per_peer,
+            reservation_duration: defaults.reservation_duration,
+            max_circuits: defaults.max_circuits,
+            max_circuits_per_peer: defaults.max_circuits_per_peer,
+            max_circuit_duration: defaults.max_circuit_duration,
+            max_circuit_bytes: defaults.max_circuit_bytes,
+        }
+    }
+}
+
+impl From<RelayServerConfig> for relay::Config {
+    fn from(config: RelayServerConfig) -> Self {
+        relay::Config {
+            max_reservations: config.max_reservations,
+            max_reservations_per_peer: config.max_reservations_per_peer,
+            reservation_duration: config.reservation_duration,
+            max_circuits: config.max_circuits,
+            max_circuits_per_peer: config.max_circuits_per_peer,
+            max_circuit_duration: config.max_circuit_duration,
+            max_circuit_bytes: config.max_circuit_bytes,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ConnectionLimitsConfig> for ConnectionLimits {
+    fn from(config: ConnectionLimitsConfig) -> Self {
+        ConnectionLimits::default()
+            .with_max_pending_incoming(config.max_pending_incoming)
+            .with_max_pending_outgoing(config.max_pending_outgoing)
+            .with_max_established_incoming(config.max_established_incoming)
+            .with_max_established_outgoing(config.max_established_outgoing)
+            .with_max_established_per_peer(config.max_established_per_peer)
+    }
 }
 
 impl Default for SwarmConfig {
     fn default() -> Self {
         Self {
             keypair: None,
+            psk: None,
             mdns_query_interval: Duration::from_secs(5),
             gossipsub_heartbeat: Duration::from_secs(1),
             initial_topics: vec![TopicName::new_unchecked(DEFAULT_TOPIC)],
             kademlia_replication: 3,
+            kademlia_bootstrap_interval: Duration::from_secs(5 * 60),
+            dht_mode: None,
+            record_ttl: Some(Duration::from_secs(48 * 60 * 60)),
+            record_replication_interval: Some(Duration::from_secs(60 * 60)),
+            record_publication_interval: Some(Duration::from_secs(22 * 60 * 60)),
+            provider_record_ttl: Some(Duration::from_secs(48 * 60 * 60)),
+            provider_publication_interval: Some(Duration::from_secs(12 * 60 * 60)),
+            enable_mdns: true,
+            agent_version: format!("peernet/{}", env!("CARGO_PKG_VERSION")),
+            ping_interval: Duration::from_secs(15),
+            connection_limits: ConnectionLimitsConfig::default(),
+            autonat: AutonatConfig::default(),
+            relay_server: RelayServerConfig::default(),
+            peer_lists: PeerListsConfig::default(),
+            record_store: RecordStoreConfig::default(),
         }
     }
 }
 
-pub fn build_swarm(config: SwarmConfig) -> PeernetResult<(Swarm<PeernetBehaviour>, PeerId)> {
-    let keypair = config.keypair.unwrap_or_else(Keypair::generate_ed25519);
+pub fn build_swarm(
+    config: SwarmConfig,
+    registry: Option<&mut Registry>,
+) -> PeernetResult<(Swarm<PeernetBehaviour>, PeerId)> {
+    let keypair = config
+        .keypair
+        .clone()
+        .unwrap_or_else(Keypair::generate_ed25519);
     let local_peer_id = PeerId::from(keypair.public());
+    let store = PeernetStore::open(local_peer_id, &config.record_store)?;
 
-    let swarm = SwarmBuilder::with_existing_identity(keypair.clone())
-        .with_tokio()
-        .with_tcp(
-            libp2p::tcp::Config::default(),
-            libp2p::noise::Config::new,
-            libp2p::yamux::Config::default,
-        )
-        .map_err(|e| PeernetError::Transport {
-            reason: e.to_string(),
-        })?
-        .with_behaviour(|key| {
-            let mut kad_config = kad::Config::new(
-                StreamProtocol::try_from_owned(KADEMLIA_PROTOCOL.to_string())
-                    .expect("valid protocol"),
-            );
-            kad_config.set_replication_factor(
-                std::num::NonZeroUsize::new(config.kademlia_replication).expect("replication > 0"),
-            );
-            kad_config.set_query_timeout(Duration::from_secs(60));
-
-            let store = MemoryStore::new(key.public().to_peer_id());
-            let mut kademlia =
-                kad::Behaviour::with_config(key.public().to_peer_id(), store, kad_config);
-            kademlia.set_mode(Some(Mode::Server));
-
-            let gossipsub_config = gossipsub::ConfigBuilder::default()
-                .heartbeat_interval(config.gossipsub_heartbeat)
-                .validation_mode(ValidationMode::Strict)
-                .message_id_fn(|msg| {
-                    use std::hash::{Hash, Hasher};
-                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                    msg.data.hash(&mut hasher);
-                    msg.topic.hash(&mut hasher);
-                    gossipsub::MessageId::from(hasher.finish().to_be_bytes().to_vec())
+    let builder = SwarmBuilder::with_existing_identity(keypair).with_tokio();
+    // QUIC brings its own encryption and can't be wrapped in pnet, so a
+    // private network runs over TCP only.
+    let swarm = match config.psk {
+        None => builder
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default,
+            )
+            .map_err(transport_error)?
+            .with_quic()
+            .with_other_transport(websocket_transport)
+            .map_err(transport_error)?
+            .with_other_transport(memory_transport)
+            .map_err(transport_error)?
+            .with_relay_client(noise::Config::new, yamux::Config::default)
+            .map_err(transport_error)?
+            .with_behaviour(|key, relay_client| {
+                build_behaviour(key, relay_client, &config, store, registry)
+            })
+            .map_err(transport_error)?
+            .with_swarm_config(swarm_config)
+            .build(),
+        Some(psk) => {
+            info!(fingerprint = %psk.fingerprint(), "private network enabled");
+            builder
+                .with_other_transport(|key| private_tcp_transport(key, psk))
+                .map_err(transport_error)?
+                .with_relay_client(noise::Config::new, yamux::Config::default)
+                .map_err(transport_error)?
+                .with_behaviour(|key, relay_client| {
+                    build_behaviour(key, relay_client, &config, store, registry)
                 })
+                .map_err(transport_error)?
+                .with_swarm_config(swarm_config)
                 .build()
-                .expect("valid config");
+        }
+    };
 
-            let gossipsub = gossipsub::Behaviour::new(
-                MessageAuthenticity::Signed(key.clone()),
-                gossipsub_config,
-            )
-            .expect("valid behaviour");
+    Ok((swarm, local_peer_id))
+}
 
-            let mdns_config = mdns::Config {
-                query_interval: config.mdns_query_interval,
-                ..Default::default()
-            };
-            let mdns = mdns::tokio::Behaviour::new(mdns_config, key.public().to_peer_id())
-                .expect("mdns init");
-
-            PeernetBehaviour {
-                kademlia,
-                gossipsub,
-                mdns,
-            }
+fn private_tcp_transport(
+    key: &Keypair,
+    psk: PreSharedKey,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(tcp::tokio::Transport::new(tcp::Config::default())
+        .and_then(move |socket, _| PnetConfig::new(psk).handshake(socket))
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(noise::Config::new(key)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+// Nodes can always dial `/ws` addresses; whether they listen on one is up to
+// the transport config.
+fn websocket_transport(
+    key: &Keypair,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(
+        websocket::Config::new(tcp::tokio::Transport::new(tcp::Config::default()))
+            .upgrade(upgrade::Version::V1Lazy)
+            .authenticate(noise::Config::new(key)?)
+            .multiplex(yamux::Config::default())
+            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+            .boxed(),
+    )
+}
+
+fn memory_transport(
+    key: &Keypair,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(MemoryTransport::default()
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(noise::Config::new(key)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+fn build_behaviour(
+    key: &Keypair,
+    relay_client: relay::client::Behaviour,
+    config: &SwarmConfig,
+    store: PeernetStore,
+    registry: Option<&mut Registry>,
+) -> PeernetBehaviour {
+    let mut kad_config = kad::Config::new(
+        StreamProtocol::try_from_owned(KADEMLIA_PROTOCOL.to_string()).expect("valid protocol"),
+    );
+    kad_config.set_replication_factor(
+        std::num::NonZeroUsize::new(config.kademlia_replication).expect("replication > 0"),
+    );
+    kad_config.set_query_timeout(Duration::from_secs(60));
+    kad_config.set_periodic_bootstrap_interval(Some(config.kademlia_bootstrap_interval));
+    kad_config.set_record_ttl(config.record_ttl);
+    kad_config.set_replication_interval(config.record_replication_interval);
+    kad_config.set_publication_interval(config.record_publication_interval);
+    kad_config.set_provider_record_ttl(config.provider_record_ttl);
+    kad_config.set_provider_publication_interval(config.provider_publication_interval);
+
+    let mut kademlia = kad::Behaviour::with_config(key.public().to_peer_id(), store, kad_config);
+    // Until AutoNAT has an answer we serve, so LANs and fresh clusters keep a
+    // working DHT.
+    kademlia.set_mode(Some(kad_mode(config.dht_mode.unwrap_or(DhtMode::Server))));
+
+    let gossipsub_config = gossipsub::ConfigBuilder::default()
+        .heartbeat_interval(config.gossipsub_heartbeat)
+        .validation_mode(ValidationMode::Strict)
+        .message_id_fn(|msg| {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            msg.data.hash(&mut hasher);
+            msg.topic.hash(&mut hasher);
+            gossipsub::MessageId::from(hasher.finish().to_be_bytes().to_vec())
         })
-        .map_err(|e| PeernetError::Transport {
-            reason: e.to_string(),
-        })?
-        .with_swarm_config(|cfg| cfg.with_idle_connection_timeout(Duration::from_secs(60)))
-        .build();
+        .build()
+        .expect("valid config");
 
-    Ok((swarm, local_peer_id))
+    let mut gossipsub =
+        gossipsub::Behaviour::new(MessageAuthenticity::Signed(key.clone()), gossipsub_config)
+            .expect("valid behaviour");
+    if let Some(registry) = registry {
+        gossipsub = gossipsub.with_metrics(
+            registry.sub_registry_with_prefix("gossipsub"),
+            gossipsub::MetricsConfig::default(),
+        );
+    }
+
+    let identify = identify::Behaviour::new(
+        identify::Config::new(IDENTIFY_PROTOCOL_VERSION.to_string(), key.public())
+            .with_agent_version(config.agent_version.clone()),
+    );
+
+    let ping = ping::Behaviour::new(ping::Config::new().with_interval(config.ping_interval));
+
+    let mdns = config.enable_mdns.then(|| {
+        let mdns_config = mdns::Config {
+            query_interval: config.mdns_query_interval,
+            ..Default::default()
+        };
+        mdns::tokio::Behaviour::new(mdns_config, key.public().to_peer_id()).expect("mdns init")
+    });
+
+    let limits = connection_limits::Behaviour::new(config.connection_limits.into());
+
+    let autonat = autonat::Behaviour::new(key.public().to_peer_id(), config.autonat.into());
+
+    let relay_server = config
+        .relay_server
+        .enabled
+        .then(|| relay::Behaviour::new(key.public().to_peer_id(), config.relay_server.into()));
+
+    let allowed = config
+        .peer_lists
+        .only_allowed
+        .then(allow_block_list::Behaviour::default);
+
+    PeernetBehaviour {
+        limits,
+        blocked: allow_block_list::Behaviour::default(),
+        allowed: allowed.into(),
+        kademlia,
+        gossipsub,
+        identify,
+        ping,
+        autonat,
+        relay_client,
+        relay_server: relay_server.into(),
+        mdns: mdns.into(),
+    }
+}
+
+pub(crate) fn kad_mode(mode: DhtMode) -> Mode {
+    match mode {
+        DhtMode::Client => Mode::Client,
+        DhtMode::Server => Mode::Server,
+    }
+}
+
+fn swarm_config(config: libp2p::swarm::Config) -> libp2p::swarm::Config {
+    config.with_idle_connection_timeout(Duration::from_secs(60))
+}
+
+fn transport_error(e: impl std::fmt::Display) -> PeernetError {
+    PeernetError::Transport {
+        reason: e.to_string(),
+    }
 }
 
 #[cfg(test)]
@@ -110,7 +394,7 @@ mod tests {
 
     #[tokio::test]
     async fn builds_swarm_with_default_config() {
-        let result = build_swarm(SwarmConfig::default());
+        let result = build_swarm(SwarmConfig::default(), None);
         assert!(result.is_ok());
         let (_, peer_id) = result.unwrap();
         assert!(peer_id.to_string().starts_with("12D3K"));
@@ -124,7 +408,7 @@ mod tests {
             keypair: Some(keypair),
             ..Default::default()
         };
-        let (_, peer_id) = build_swarm(config).unwrap();
+        let (_, peer_id) = build_swarm(config, None).unwrap();
         assert_eq!(peer_id, expected);
     }
 }
@@ -0,0 +1,728 @@
+use peernet_core::{DhtMode, Multiaddr, PeernetError, PeernetResult, TopicName};
+use peernet_network::{
+    AutonatConfig, ConnectionLimitsConfig, NetworkConfig, PeerListsConfig, RecordStoreBackend,
+    RecordStoreConfig, RelayServerConfig, SwarmConfig, TransportConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::{Table, Value};
+use tracing::warn;
+
+const ENV_PREFIX: &str = "PEERNET_";
+
+// Every setting, as its dotted path through the sections. Environment
+// variables are checked against this; a test keeps it in step with the structs.
+const SETTINGS: &[&str] = &[
+    "port",
+    "identity",
+    "psk",
+    "bootstrap_peers",
+    "relays",
+    "event_capacity",
+    "record_expiry_interval",
+    "metrics_addr",
+    "transport.tcp",
+    "transport.quic",
+    "transport.websocket",
+    "transport.websocket_port",
+    "swarm.enable_mdns",
+    "swarm.agent_version",
+    "swarm.ping_interval",
+    "swarm.mdns_query_interval",
+    "swarm.gossipsub_heartbeat",
+    "swarm.initial_topics",
+    "swarm.kademlia_replication",
+    "swarm.kademlia_bootstrap_interval",
+    "swarm.dht_mode",
+    "swarm.record_ttl",
+    "swarm.record_replication_interval",
+    "swarm.record_publication_interval",
+    "swarm.provider_record_ttl",
+    "swarm.provider_publication_interval",
+    "swarm.connection_limits.max_pending_incoming",
+    "swarm.connection_limits.max_pending_outgoing",
+    "swarm.connection_limits.max_established_incoming",
+    "swarm.connection_limits.max_established_outgoing",
+    "swarm.connection_limits.max_established_per_peer",
+    "swarm.autonat.boot_delay",
+    "swarm.autonat.retry_interval",
+    "swarm.autonat.refresh_interval",
+    "swarm.autonat.only_global_ips",
+    "swarm.relay_server.enabled",
+    "swarm.relay_server.max_reservations",
+    "swarm.relay_server.max_reservations_per_peer",
+    "swarm.relay_server.reservation_duration",
+    "swarm.relay_server.max_circuits",
+    "swarm.relay_server.max_circuits_per_peer",
+    "swarm.relay_server.max_circuit_duration",
+    "swarm.relay_server.max_circuit_bytes",
+    "swarm.peer_lists.data_dir",
+    "swarm.peer_lists.only_allowed",
+    "swarm.record_store.data_dir",
+    "swarm.record_store.max_records",
+    "swarm.record_store.max_value_bytes",
+    "swarm.record_store.max_providers_per_key",
+    "swarm.record_store.max_provided_keys",
+];
+
+// File, environment and CLI all layer over these defaults, which are taken
+// from NetworkConfig so the two can't drift apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub psk: Option<PathBuf>,
+    pub bootstrap_peers: Vec<Multiaddr>,
+    pub relays: Vec<Multiaddr>,
+    pub event_capacity: usize,
+    #[serde(with = "humantime_serde")]
+    pub record_expiry_interval: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_addr: Option<SocketAddr>,
+    pub transport: TransportSection,
+    pub swarm: SwarmSection,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransportSection {
+    pub tcp: bool,
+    pub quic: bool,
+    pub websocket: bool,
+    pub websocket_port: u16,
+}
+
+// Optional durations use zero for "disabled".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwarmSection {
+    pub enable_mdns: bool,
+    pub agent_version: String,
+    #[serde(with = "humantime_serde")]
+    pub ping_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub mdns_query_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub gossipsub_heartbeat: Duration,
+    pub initial_topics: Vec<String>,
+    pub kademlia_replication: usize,
+    #[serde(with = "humantime_serde")]
+    pub kademlia_bootstrap_interval: Duration,
+    pub dht_mode: DhtModeSetting,
+    #[serde(with = "humantime_serde")]
+    pub record_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub record_replication_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub record_publication_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub provider_record_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub provider_publication_interval: Duration,
+    pub connection_limits: ConnectionLimitsSection,
+    pub autonat: AutonatSection,
+    pub relay_server: RelayServerSection,
+    pub peer_lists: PeerListsSection,
+    pub record_store: RecordStoreSection,
+}
+
+// Zero means unlimited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionLimitsSection {
+    pub max_pending_incoming: u32,
+    pub max_pending_outgoing: u32,
+    pub max_established_incoming: u32,
+    pub max_established_outgoing: u32,
+    pub max_established_per_peer: u32,
+}
+
+// `auto` lets AutoNAT move a node behind NAT into DHT client mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DhtModeSetting {
+    Auto,
+    Client,
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutonatSection {
+    #[serde(with = "humantime_serde")]
+    pub boot_delay: Duration,
+    #[serde(with = "humantime_serde")]
+    pub retry_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+    pub only_global_ips: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelayServerSection {
+    pub enabled: bool,
+    pub max_reservations: usize,
+    pub max_reservations_per_peer: usize,
+    #[serde(with = "humantime_serde")]
+    pub reservation_duration: Duration,
+    pub max_circuits: usize,
+    pub max_circuits_per_peer: usize,
+    #[serde(with = "humantime_serde")]
+    pub max_circuit_duration: Duration,
+    pub max_circuit_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PeerListsSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    pub only_allowed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordStoreSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    pub max_records: usize,
+    pub max_value_bytes: usize,
+    pub max_providers_per_key: usize,
+    pub max_provided_keys: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_network(&NetworkConfig::default())
+    }
+}
+
+impl Default for TransportSection {
+    fn default() -> Self {
+        Config::default().transport
+    }
+}
+
+impl Default for SwarmSection {
+    fn default() -> Self {
+        Config::default().swarm
+    }
+}
+
+impl Default for ConnectionLimitsSection {
+    fn default() -> Self {
+        Config::default().swarm.connection_limits
+    }
+}
+
+impl Default for AutonatSection {
+    fn default() -> Self {
+        Config::default().swarm.autonat
+    }
+}
+
+impl Default for RelayServerSection {
+    fn default() -> Self {
+        Config::default().swarm.relay_server
+    }
+}
+
+impl Default for PeerListsSection {
+    fn default() -> Self {
+        Config::default().swarm.peer_lists
+    }
+}
+
+impl Default for RecordStoreSection {
+    fn default() -> Self {
+        Config::default().swarm.record_store
+    }
+}
+
+impl Config {
+    pub fn from_network(config: &NetworkConfig) -> Self {
+        let swarm = &config.swarm;
+        let store = &swarm.record_store;
+        let limits = &swarm.connection_limits;
+        let relay = &swarm.relay_server;
+        Self {
+            port: config.port,
+            identity: None,
+            psk: None,
+            bootstrap_peers: config.bootstrap_peers.clone(),
+            relays: config.relays.clone(),
+            event_capacity: config.event_capacity,
+            record_expiry_interval: config.record_expiry_interval,
+            metrics_addr: config.metrics_addr,
+            transport: TransportSection {
+                tcp: config.transport.tcp,
+                quic: config.transport.quic,
+                websocket: config.transport.websocket,
+                websocket_port: config.transport.websocket_port,
+            },
+            swarm: SwarmSection {
+                enable_mdns: swarm.enable_mdns,
+                agent_version: swarm.agent_version.clone(),
+                ping_interval: swarm.ping_interval,
+                mdns_query_interval: swarm.mdns_query_interval,
+                gossipsub_heartbeat: swarm.gossipsub_heartbeat,
+                initial_topics: swarm.initial_topics.iter().map(|t| t.to_string()).collect(),
+                kademlia_replication: swarm.kademlia_replication,
+                kademlia_bootstrap_interval: swarm.kademlia_bootstrap_interval,
+                dht_mode: match swarm.dht_mode {
+                    None => DhtModeSetting::Auto,
+                    Some(DhtMode::Client) => DhtModeSetting::Client,
+                    Some(DhtMode::Server) => DhtModeSetting::Server,
+                },
+                record_ttl: swarm.record_ttl.unwrap_or_default(),
+                record_replication_interval: swarm.record_replication_interval.unwrap_or_default(),
+                record_publication_interval: swarm.record_publication_interval.unwrap_or_default(),
+                provider_record_ttl: swarm.provider_record_ttl.unwrap_or_default(),
+                provider_publication_interval: swarm
+                    .provider_publication_interval
+                    .unwrap_or_default(),
+                connection_limits: ConnectionLimitsSection {
+                    max_pending_incoming: limits.max_pending_incoming.unwrap_or_default(),
+                    max_pending_outgoing: limits.max_pending_outgoing.unwrap_or_default(),
+                    max_established_incoming: limits.max_established_incoming.unwrap_or_default(),
+                    max_established_outgoing: limits.max_established_outgoing.unwrap_or_default(),
+                    max_established_per_peer: limits.max_established_per_peer.unwrap_or_default(),
+                },
+                autonat: AutonatSection {
+                    boot_delay: swarm.autonat.boot_delay,
+                    retry_interval: swarm.autonat.retry_interval,
+                    refresh_interval: swarm.autonat.refresh_interval,
+                    only_global_ips: swarm.autonat.only_global_ips,
+                },
+                relay_server: RelayServerSection {
+                    enabled: relay.enabled,
+                    max_reservations: relay.max_reservations,
+                    max_reservations_per_peer: relay.max_reservations_per_peer,
+                    reservation_duration: relay.reservation_duration,
+                    max_circuits: relay.max_circuits,
+                    max_circuits_per_peer: relay.max_circuits_per_peer,
+                    max_circuit_duration: relay.max_circuit_duration,
+                    max_circuit_bytes: relay.max_circuit_bytes,
+                },
+                peer_lists: PeerListsSection {
+                    data_dir: swarm.peer_lists.data_dir.clone(),
+                    only_allowed: swarm.peer_lists.only_allowed,
+                },
+                record_store: RecordStoreSection {
+                    data_dir: match &store.backend {
+                        RecordStoreBackend::Memory => None,
+                        RecordStoreBackend::Disk { data_dir } => Some(data_dir.clone()),
+                    },
+                    max_records: store.max_records,
+                    max_value_bytes: store.max_value_bytes,
+                    max_providers_per_key: store.max_providers_per_key,
+                    max_provided_keys: store.max_provided_keys,
+                },
+            },
+        }
+    }
+
+    // The keypair and pre-shared key are left unset; they are loaded from
+    // `identity` and `psk` when the node starts.
+    pub fn to_network(&self) -> PeernetResult<NetworkConfig> {
+        for addr in &self.bootstrap_peers {
+            if peernet_network::peer_id_of(addr).is_none() {
+                return Err(config_error(format!(
+                    "bootstrap peer {addr} must end in /p2p/<peer-id>"
+                )));
+            }
+        }
+        for addr in &self.relays {
+            if peernet_network::peer_id_of(addr).is_none() {
+                return Err(config_error(format!(
+                    "relay {addr} must end in /p2p/<peer-id>"
+                )));
+            }
+        }
+        if self.event_capacity == 0 {
+            return Err(config_error("event_capacity must be greater than 0"));
+        }
+        if self.swarm.kademlia_replication == 0 {
+            return Err(config_error(
+                "swarm.kademlia_replication must be greater than 0",
+            ));
+        }
+        let initial_topics = self
+            .swarm
+            .initial_topics
+            .iter()
+            .map(|t| TopicName::new(t.as_str()))
+            .collect::<PeernetResult<Vec<_>>>()?;
+
+        let swarm = &self.swarm;
+        let store = &swarm.record_store;
+        let limits = &swarm.connection_limits;
+        let relay = &swarm.relay_server;
+        Ok(NetworkConfig {
+            port: self.port,
+            transport: TransportConfig {
+                tcp: self.transport.tcp,
+                quic: self.transport.quic,
+                websocket: self.transport.websocket,
+                websocket_port: self.transport.websocket_port,
+                // In-process only, so not something a config file can ask for.
+                memory: false,
+            },
+            bootstrap_peers: self.bootstrap_peers.clone(),
+            relays: self.relays.clone(),
+            event_capacity: self.event_capacity,
+            record_expiry_interval: self.record_expiry_interval,
+            metrics_addr: self.metrics_addr,
+            swarm: SwarmConfig {
+                keypair: None,
+                psk: None,
+                mdns_query_interval: swarm.mdns_query_interval,
+                gossipsub_heartbeat: swarm.gossipsub_heartbeat,
+                initial_topics,
+                kademlia_replication: swarm.kademlia_replication,
+                kademlia_bootstrap_interval: swarm.kademlia_bootstrap_interval,
+                dht_mode: match swarm.dht_mode {
+                    DhtModeSetting::Auto => None,
+                    DhtModeSetting::Client => Some(DhtMode::Client),
+                    DhtModeSetting::Server => Some(DhtMode::Server),
+                },
+                record_ttl: enabled(swarm.record_ttl),
+                record_replication_interval: enabled(swarm.record_replication_interval),
+                record_publication_interval: enabled(swarm.record_publication_interval),
+                provider_record_ttl: enabled(swarm.provider_record_ttl),
+                provider_publication_interval: enabled(swarm.provider_publication_interval),
+                enable_mdns: swarm.enable_mdns,
+                agent_version: swarm.agent_version.clone(),
+                ping_interval: swarm.ping_interval,
+                connection_limits: ConnectionLimitsConfig {
+                    max_pending_incoming: limited(limits.max_pending_incoming),
+                    max_pending_outgoing: limited(limits.max_pending_outgoing),
+                    max_established_incoming: limited(limits.max_established_incoming),
+                    max_established_outgoing: limited(limits.max_established_outgoing),
+                    max_established_per_peer: limited(limits.max_established_per_peer),
+                },
+                autonat: AutonatConfig {
+                    boot_delay: swarm.autonat.boot_delay,
+                    retry_interval: swarm.autonat.retry_interval,
+                    refresh_interval: swarm.autonat.refresh_interval,
+                    only_global_ips: swarm.autonat.only_global_ips,
+                },
+                relay_server: RelayServerConfig {
+                    enabled: relay.enabled,
+                    max_reservations: relay.max_reservations,
+                    max_reservations_per_peer: relay.max_reservations_per_peer,
+                    reservation_duration: relay.reservation_duration,
+                    max_circuits: relay.max_circuits,
+                    max_circuits_per_peer: relay.max_circuits_per_peer,
+                    max_circuit_duration: relay.max_circuit_duration,
+                    max_circuit_bytes: relay.max_circuit_bytes,
+                },
+                peer_lists: PeerListsConfig {
+                    data_dir: swarm.peer_lists.data_dir.clone(),
+                    only_allowed: swarm.peer_lists.only_allowed,
+                },
+                record_store: RecordStoreConfig {
+                    backend: store
+                        .data_dir
+                        .clone()
+                        .map(|data_dir| RecordStoreBackend::Disk { data_dir })
+                        .unwrap_or_default(),
+                    max_records: store.max_records,
+                    max_value_bytes: store.max_value_bytes,
+                    max_providers_per_key: store.max_providers_per_key,
+                    max_provided_keys: store.max_provided_keys,
+                },
+            },
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("config serializes")
+    }
+}
+
+// `psk` is the `--psk` flag. It is applied here rather than over the result
+// because a pre-shared key also changes the transport defaults.
+pub fn load(
+    path: Option<&Path>,
+    env: impl IntoIterator<Item = (String, String)>,
+    psk: Option<&Path>,
+) -> PeernetResult<Config> {
+    let mut table = match path {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| config_error(format!("{}: {e}", path.display())))?;
+            contents
+                .parse::<Table>()
+                .map_err(|e| config_error(format!("{}: {e}", path.display())))?
+        }
+        None => Table::new(),
+    };
+    merge(&mut table, env_table(env));
+    if psk.is_some() || table.contains_key("psk") {
+        private_network_defaults(&mut table);
+    }
+
+    let mut config: Config = Value::Table(table).try_into().map_err(config_error)?;
+    if let Some(psk) = psk {
+        config.psk = Some(psk.to_path_buf());
+    }
+    Ok(config)
+}
+
+// QUIC can't carry a pre-shared key, so a private node leaves it off unless
+// the file or environment asks for it, in which case validation rejects it.
+fn private_network_defaults(table: &mut Table) {
+    if let Value::Table(transport) = table
+        .entry("transport")
+        .or_insert_with(|| Value::Table(Table::new()))
+    {
+        transport.entry("quic").or_insert(Value::Boolean(false));
+    }
+}
+
+// PEERNET_SWARM__ENABLE_MDNS=false sets `enable_mdns` in the `[swarm]` table.
+// The prefix is shared with wrapper scripts and other tools, so a variable
+// that doesn't name a setting is skipped with a warning rather than failing
+// the whole load the way an unknown key in the file does.
+fn env_table(env: impl IntoIterator<Item = (String, String)>) -> Table {
+    let mut table = Table::new();
+    for (name, raw) in env {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<String> = key.split("__").map(str::to_lowercase).collect();
+        if !SETTINGS.contains(&path.join(".").as_str()) {
+            warn!(%name, "ignoring environment variable that is not a config setting");
+            continue;
+        }
+        let Some((field, sections)) = path.split_last() else {
+            continue;
+        };
+
+        let mut entry = Table::new();
+        entry.insert(field.clone(), env_value(&raw));
+        for section in sections.iter().rev() {
+            let mut parent = Table::new();
+            parent.insert(section.clone(), Value::Table(entry));
+            entry = parent;
+        }
+        merge(&mut table, entry);
+    }
+    table
+}
+
+// Values are read as TOML where possible, so numbers, booleans and arrays work
+// unquoted; anything else is taken as a string.
+fn env_value(raw: &str) -> Value {
+    format!("value = {raw}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn enabled(interval: Duration) -> Option<Duration> {
+    (!interval.is_zero()).then_some(interval)
+}
+
+fn limited(limit: u32) -> Option<u32> {
+    (limit != 0).then_some(limit)
+}
+
+fn config_error(reason: impl ToString) -> PeernetError {
+    PeernetError::Config {
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_round_trip_through_toml() {
+        let config = Config::default();
+        let parsed: Config = toml::from_str(&config.to_toml()).unwrap();
+        assert_eq!(parsed, config);
+
+        let network = config.to_network().unwrap();
+        assert_eq!(Config::from_network(&network), config);
+    }
+
+    #[test]
+    fn environment_overrides_file() {
+        let path = std::env::temp_dir().join(format!("peernet-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+port = 4001
+metrics_addr = "127.0.0.1:9464"
+
+[swarm]
+gossipsub_heartbeat = "500ms"
+initial_topics = ["chat"]
+record_ttl = "0s"
+
+[swarm.record_store]
+max_records = 10
+"#,
+        )
+        .unwrap();
+
+        let config = load(
+            Some(&path),
+            env(&[
+                ("PEERNET_PORT", "4002"),
+                ("PEERNET_SWARM__ENABLE_MDNS", "false"),
+                ("PEERNET_SWARM__RECORD_STORE__DATA_DIR", "/var/lib/peernet"),
+                ("PEERNET_TRANSPORT__QUIC", "false"),
+                ("PEERNET_SWARM__DHT_MODE", "client"),
+                (
+                    "PEERNET_SWARM__CONNECTION_LIMITS__MAX_ESTABLISHED_PER_PEER",
+                    "8",
+                ),
+                ("RUST_LOG", "debug"),
+            ]),
+            None,
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.port, 4002);
+        assert_eq!(config.metrics_addr, Some("127.0.0.1:9464".parse().unwrap()));
+        assert!(!config.transport.quic);
+        assert!(config.transport.tcp);
+        assert!(!config.swarm.enable_mdns);
+        assert_eq!(config.swarm.record_store.max_records, 10);
+
+        let network = config.to_network().unwrap();
+        assert_eq!(
+            network.swarm.gossipsub_heartbeat,
+            Duration::from_millis(500)
+        );
+        assert_eq!(network.swarm.initial_topics[0].as_str(), "chat");
+        assert_eq!(network.swarm.record_ttl, None);
+        assert_eq!(network.swarm.dht_mode, Some(DhtMode::Client));
+        assert_eq!(
+            network.swarm.connection_limits.max_established_per_peer,
+            Some(8)
+        );
+        assert_eq!(
+            network.swarm.connection_limits.max_established_incoming,
+            None
+        );
+        assert!(matches!(
+            network.swarm.record_store.backend,
+            RecordStoreBackend::Disk { ref data_dir } if data_dir == Path::new("/var/lib/peernet")
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_and_invalid_settings() {
+        let path = std::env::temp_dir().join(format!("peernet-bad-{}.toml", std::process::id()));
+        std::fs::write(&path, "[swarm]\nmdns = false\n").unwrap();
+        let result = load(Some(&path), Vec::new(), None);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+
+        assert!(load(None, env(&[("PEERNET_PORT", "not-a-port")]), None).is_err());
+
+        let config = load(
+            None,
+            env(&[("PEERNET_SWARM__KADEMLIA_REPLICATION", "0")]),
+            None,
+        )
+        .unwrap();
+        assert!(config.to_network().is_err());
+    }
+
+    #[test]
+    fn ignores_unrelated_environment_variables() {
+        let config = load(
+            None,
+            env(&[
+                ("PEERNET_HOME", "/opt/peernet"),
+                ("PEERNET_SWARM__MDNS", "false"),
+                ("PEERNET_LOGGING__LEVEL", "debug"),
+                ("PEERNET_PORT", "4002"),
+            ]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.port, 4002);
+        assert!(config.swarm.enable_mdns);
+    }
+
+    #[test]
+    fn settings_list_matches_config() {
+        fn leaves(table: &Table, prefix: &str, out: &mut Vec<String>) {
+            for (key, value) in table {
+                let path = format!("{prefix}{key}");
+                match value {
+                    Value::Table(section) => leaves(section, &format!("{path}."), out),
+                    _ => out.push(path),
+                }
+            }
+        }
+
+        let mut config = Config {
+            identity: Some("identity.key".into()),
+            psk: Some("swarm.key".into()),
+            metrics_addr: Some("127.0.0.1:9464".parse().unwrap()),
+            ..Default::default()
+        };
+        config.swarm.peer_lists.data_dir = Some("data".into());
+        config.swarm.record_store.data_dir = Some("data".into());
+
+        let mut found = Vec::new();
+        leaves(&toml::from_str(&config.to_toml()).unwrap(), "", &mut found);
+        found.sort();
+        let mut expected: Vec<String> = SETTINGS.iter().map(|s| s.to_string()).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn psk_turns_quic_off_unless_asked_for() {
+        let config = load(None, env(&[("PEERNET_PSK", "swarm.key")]), None).unwrap();
+        assert!(!config.transport.quic);
+        assert!(config.transport.tcp);
+
+        let config = load(
+            None,
+            env(&[("PEERNET_TRANSPORT__QUIC", "true")]),
+            Some(Path::new("swarm.key")),
+        )
+        .unwrap();
+        assert_eq!(config.psk, Some(PathBuf::from("swarm.key")));
+        assert!(config.transport.quic);
+
+        assert!(load(None, Vec::new(), None).unwrap().transport.quic);
+    }
+}
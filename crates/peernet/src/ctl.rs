@@ -0,0 +1,148 @@
+use crate::{
+    daemon::control_error,
+    rpc::{
+        self, EventsParams, KeyParams, Notification, PublishParams, PutParams, Request, Response,
+        TopicParams,
+    },
+};
+use clap::Subcommand;
+use peernet_core::{PeernetError, PeernetResult};
+use serde_json::Value;
+use std::path::Path;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    Status,
+    Peers,
+    Put {
+        key: String,
+        value: String,
+        #[arg(long, value_name = "SECONDS")]
+        ttl: Option<u64>,
+    },
+    Get {
+        key: String,
+    },
+    Publish {
+        message: String,
+        #[arg(long)]
+        topic: Option<String>,
+    },
+    Subscribe {
+        topic: String,
+    },
+    Unsubscribe {
+        topic: String,
+    },
+    Events {
+        #[arg(long)]
+        topic: Option<String>,
+    },
+    Shutdown,
+}
+
+impl CtlCommand {
+    fn request(&self) -> Request {
+        let (method, params) = match self {
+            Self::Status => ("status", Value::Null),
+            Self::Peers => ("peers", Value::Null),
+            Self::Put { key, value, ttl } => (
+                "put",
+                to_params(PutParams {
+                    key: key.clone(),
+                    value: rpc::encode_bytes(value.as_bytes()),
+                    ttl: *ttl,
+                }),
+            ),
+            Self::Get { key } => ("get", to_params(KeyParams { key: key.clone() })),
+            Self::Publish { message, topic } => (
+                "publish",
+                to_params(PublishParams {
+                    topic: topic.clone(),
+                    payload: rpc::encode_bytes(message.as_bytes()),
+                }),
+            ),
+            Self::Subscribe { topic } => (
+                "subscribe",
+                to_params(TopicParams {
+                    topic: topic.clone(),
+                }),
+            ),
+            Self::Unsubscribe { topic } => (
+                "unsubscribe",
+                to_params(TopicParams {
+                    topic: topic.clone(),
+                }),
+            ),
+            Self::Events { topic } => (
+                "events",
+                to_params(EventsParams {
+                    topic: topic.clone(),
+                }),
+            ),
+            Self::Shutdown => ("shutdown", Value::Null),
+        };
+        Request::new(1, method, params)
+    }
+}
+
+fn to_params(params: impl serde::Serialize) -> Value {
+    serde_json::to_value(params).expect("params serialize")
+}
+
+pub async fn run(socket: &Path, command: CtlCommand) -> PeernetResult<()> {
+    let stream = UnixStream::connect(socket)
+        .await
+        .map_err(|e| control_error(socket, e))?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let mut request = serde_json::to_vec(&command.request()).expect("request serializes");
+    request.push(b'\n');
+    writer
+        .write_all(&request)
+        .await
+        .map_err(|e| control_error(socket, e))?;
+
+    let line = lines
+        .next_line()
+        .await
+        .map_err(|e| control_error(socket, e))?
+        .ok_or_else(|| control_error(socket, "daemon closed the connection"))?;
+    let response: Response = serde_json::from_str(&line).map_err(|e| control_error(socket, e))?;
+
+    if let Some(error) = response.error {
+        return Err(PeernetError::Control {
+            reason: error.message,
+        });
+    }
+    let result = response.result.unwrap_or(Value::Null);
+
+    match command {
+        CtlCommand::Get { .. } => {
+            let value = result["value"].as_str().unwrap_or_default();
+            let bytes = rpc::decode_bytes(value)
+                .map_err(|e| PeernetError::Control { reason: e.message })?;
+            println!("{}", String::from_utf8_lossy(&bytes));
+        }
+        CtlCommand::Events { .. } => {
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<Notification>(&line) {
+                    Ok(notification) => println!("{}", notification.params),
+                    Err(e) => return Err(control_error(socket, e)),
+                }
+            }
+        }
+        _ if result.is_null() => {}
+        _ => println!(
+            "{}",
+            serde_json::to_string_pretty(&result).expect("json value serializes")
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use crate::{
+    output,
+    rpc::{
+        self, EventsParams, KeyParams, Notification, PublishParams, PutParams, Request, Response,
+        RpcError, TopicParams,
+    },
+};
+use peernet_core::{
+    DhtKey, DhtValue, GossipPayload, Multiaddr, NetworkEvent, PeerId, PeernetError, PeernetResult,
+    TopicName,
+};
+use peernet_network::{EventFilter, NetworkHandle};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::{
+    fs::{DirBuilder, Permissions},
+    os::unix::fs::{DirBuilderExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream, unix::OwnedWriteHalf},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+const SOCKET_NAME: &str = "peernet.sock";
+
+pub struct NodeStatus {
+    pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
+}
+
+// $XDG_RUNTIME_DIR is private to the user; without it the socket goes under
+// ~/.peernet rather than a directory every local user can write to.
+pub fn default_socket_path() -> PeernetResult<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(PathBuf::from(dir).join(SOCKET_NAME));
+    }
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".peernet").join(SOCKET_NAME)),
+        None => Err(PeernetError::Control {
+            reason: "neither XDG_RUNTIME_DIR nor HOME is set; pass --socket".into(),
+        }),
+    }
+}
+
+pub async fn serve(
+    network: &mut NetworkHandle,
+    socket: &Path,
+    status: NodeStatus,
+    cancel_token: CancellationToken,
+) -> PeernetResult<()> {
+    let listener = bind(socket).await?;
+    info!(socket = %socket.display(), "control socket listening");
+
+    let status = Arc::new(status);
+    loop {
+        tokio::select! {
+            () = cancel_token.cancelled() => {
+                let _ = network.shutdown().await;
+                while let Some(event) = network.recv().await {
+                    if matches!(event, NetworkEvent::ShutdownComplete) {
+                        break;
+                    }
+                }
+                break;
+            }
+
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(
+                        stream,
+                        network.clone(),
+                        status.clone(),
+                        cancel_token.clone(),
+                    ));
+                }
+                Err(e) => warn!(?e, "failed to accept control connection"),
+            },
+
+            event = network.recv() => match event {
+                Some(NetworkEvent::ShutdownComplete) | None => break,
+                Some(_) => {}
+            },
+        }
+    }
+
+    let _ = std::fs::remove_file(socket);
+    Ok(())
+}
+
+async fn bind(socket: &Path) -> PeernetResult<UnixListener> {
+    if socket.exists() {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(control_error(socket, "a daemon is already listening"));
+        }
+        std::fs::remove_file(socket).map_err(|e| control_error(socket, e))?;
+    }
+
+    let parent = socket
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(parent)
+        .map_err(|e| control_error(socket, e))?;
+
+    // The socket is bound inside a directory only we can enter and moved into
+    // place once it is owner-only, so no other user can connect in between.
+    let staging = parent.join(format!(".peernet-{}", std::process::id()));
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .map_err(|e| control_error(socket, e))?;
+    let staged = staging.join(SOCKET_NAME);
+    let result = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, socket)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+
+    result.map_err(|e| control_error(socket, e))
+}
+
+pub fn control_error(socket: &Path, reason: impl ToString) -> PeernetError {
+    PeernetError::Control {
+        reason: format!("{}: {}", socket.display(), reason.to_string()),
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    network: NetworkHandle,
+    status: Arc<NodeStatus>,
+    cancel_token: CancellationToken,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let response = Response::new(Value::Null, Err(RpcError::parse(e)));
+                if write_line(&mut writer, &response).await.is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        if request.method == "events" {
+            let filter = match rpc::params::<EventsParams>(request.params).and_then(event_filter) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    let _ = write_line(&mut writer, &Response::new(request.id, Err(e))).await;
+                    continue;
+                }
+            };
+            let mut events = network.subscribe_events(filter);
+            if write_line(&mut writer, &Response::new(request.id, Ok(Value::Null)))
+                .await
+                .is_err()
+            {
+                return;
+            }
+
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Some(event) => {
+                            if write_line(&mut writer, &Notification::event(&event)).await.is_err() {
+                                return;
+                            }
+                        }
+                        None => return,
+                    },
+                    line = lines.next_line() => {
+                        if !matches!(line, Ok(Some(_))) {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+
+        let outcome = dispatch(
+            &network,
+            &status,
+            &request.method,
+            request.params,
+            &cancel_token,
+        )
+        .await;
+        if write_line(&mut writer, &Response::new(request.id, outcome))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, message: &impl Serialize) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+fn event_filter(params: EventsParams) -> Result<EventFilter, RpcError> {
+    match params.topic {
+        Some(topic) => Ok(EventFilter::Topic(
+            TopicName::new(topic).map_err(RpcError::invalid_params)?,
+        )),
+        None => Ok(EventFilter::All),
+    }
+}
+
+async fn dispatch(
+    network: &NetworkHandle,
+    status: &NodeStatus,
+    method: &str,
+    params: Value,
+    cancel_token: &CancellationToken,
+) -> Result<Value, RpcError> {
+    match method {
+        "status" => {
+            let peers = network
+                .connected_peers()
+                .await
+                .map_err(RpcError::command_failed)?;
+            let topics = network
+                .subscriptions()
+                .await
+                .map_err(RpcError::command_failed)?;
+            Ok(json!({
+                "peer_id": status.peer_id.to_string(),
+                "listen_addrs": status.listen_addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+                "connected_peers": peers.len(),
+                "subscriptions": topics.iter().map(|t| t.as_str()).collect::<Vec<_>>(),
+            }))
+        }
+
+        "peers" => {
+            let peers = network.peers().await.map_err(RpcError::command_failed)?;
+            Ok(peers.iter().map(output::encode_peer).collect())
+        }
+
+        "put" => {
+            let params: PutParams = rpc::params(params)?;
+            let key = DhtKey::new(params.key).map_err(RpcError::invalid_params)?;
+            let value = DhtValue::new(rpc::decode_bytes(&params.value)?)
+                .map_err(RpcError::invalid_params)?;
+            let stored = match params.ttl {
+                Some(secs) => {
+                    network
+                        .put_with_ttl(key, value, Duration::from_secs(secs))
+                        .await
+                }
+                None => network.put(key, value).await,
+            };
+            stored.map_err(RpcError::command_failed)?;
+            Ok(Value::Null)
+        }
+
+        "get" => {
+            let params: KeyParams = rpc::params(params)?;
+            let key = DhtKey::new(params.key).map_err(RpcError::invalid_params)?;
+            let value = network.get(key).await.map_err(RpcError::command_failed)?;
+            Ok(json!({ "value": rpc::encode_bytes(value.as_bytes()) }))
+        }
+
+        "publish" => {
+            let params: PublishParams = rpc::params(params)?;
+            let payload = GossipPayload::new(rpc::decode_bytes(&params.payload)?)
+                .map_err(RpcError::invalid_params)?;
+            let published = match params.topic {
+                Some(topic) => {
+                    let topic = TopicName::new(topic).map_err(RpcError::invalid_params)?;
+                    network.publish_to(topic, payload).await
+                }
+                None => network.publish(payload).await,
+            };
+            published.map_err(RpcError::command_failed)?;
+            Ok(Value::Null)
+        }
+
+        "subscribe" | "unsubscribe" => {
+            let params: TopicParams = rpc::params(params)?;
+            let topic = TopicName::new(params.topic).map_err(RpcError::invalid_params)?;
+            let changed = if method == "subscribe" {
+                network.subscribe(topic).await
+            } else {
+                network.unsubscribe(topic).await
+            };
+            changed.map_err(RpcError::command_failed)?;
+            Ok(Value::Null)
+        }
+
+        "shutdown" => {
+            cancel_token.cancel();
+            Ok(Value::Null)
+        }
+
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
@@ -1,76 +1,352 @@
-use clap::Parser;
-use peernet_core::{DhtKey, DhtValue, GossipPayload, NetworkEvent, PeernetError, PeernetResult};
+use clap::{Parser, Subcommand};
+use peernet_core::{
+    DhtKey, DhtValue, GossipPayload, Multiaddr, NetworkEvent, PeerId, PeernetError, PeernetResult,
+    TopicName,
+};
 use peernet_network::{NetworkConfig, NetworkHandle};
-use std::io::{self, BufRead, Write};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::{Level, info, warn};
 use tracing_subscriber::{EnvFilter, fmt};
 
+mod config;
+#[cfg(unix)]
+mod ctl;
+#[cfg(unix)]
+mod daemon;
+mod oneshot;
+mod output;
+mod rpc;
+
+use config::Config;
+use output::OutputFormat;
+
+// Round trips `ping` waits for when no count is given.
+const DEFAULT_PING_COUNT: u32 = 3;
+
 #[derive(Parser, Debug)]
 #[command(name = "peernet")]
 struct Args {
-    #[arg(short, long, default_value = "0")]
-    port: u16,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    #[arg(long, value_name = "PATH")]
+    identity: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH")]
+    psk: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    no_quic: bool,
+
+    #[arg(long, value_name = "PORT")]
+    websocket_port: Option<u16>,
+
+    #[arg(long)]
+    no_mdns: bool,
+
+    #[arg(long = "bootstrap", value_name = "MULTIADDR", value_parser = parse_bootstrap_addr)]
+    bootstrap: Vec<Multiaddr>,
+
+    #[arg(long = "relay", value_name = "MULTIADDR", value_parser = parse_bootstrap_addr)]
+    relays: Vec<Multiaddr>,
+
+    #[arg(long)]
+    relay_server: bool,
+
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+
+    #[arg(long, value_enum, default_value_t)]
+    output: OutputFormat,
 
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[cfg(unix)]
+    Daemon {
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    #[cfg(unix)]
+    Ctl {
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: ctl::CtlCommand,
+    },
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    #[command(flatten)]
+    OneShot(oneshot::OneShotCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    Print,
+}
+
 #[derive(Debug)]
 enum InputEvent {
-    Send(GossipPayload),
-    Put { key: DhtKey, value: DhtValue },
-    Get { key: DhtKey },
+    Send {
+        topic: Option<TopicName>,
+        payload: GossipPayload,
+    },
+    Join(TopicName),
+    Leave(TopicName),
+    Topics,
+    Put {
+        key: DhtKey,
+        value: DhtValue,
+        ttl: Option<Duration>,
+    },
+    Get {
+        key: DhtKey,
+    },
+    Ping {
+        peer: String,
+        count: u32,
+    },
+    Peers,
+    Ban {
+        peer: String,
+    },
+    Unban {
+        peer: String,
+    },
+    Bans,
+    Dial {
+        addr: Multiaddr,
+    },
+    DialPeer {
+        peer_id: PeerId,
+    },
+    Closest {
+        key: DhtKey,
+    },
     Quit,
 }
 
 #[tokio::main]
-async fn main() -> PeernetResult<()> {
-    let args = Args::parse();
+async fn main() -> PeernetResult<ExitCode> {
+    let mut args = Args::parse();
     init_tracing(args.verbose);
 
-    let cancel_token = CancellationToken::new();
-    spawn_signal_handler(cancel_token.clone());
-
-    let config = NetworkConfig {
-        port: args.port,
-        ..Default::default()
-    };
-    let mut network = peernet_network::spawn(config, cancel_token.clone());
-
-    match network.recv().await {
-        Some(NetworkEvent::Started {
-            local_peer_id,
-            listening_on,
-        }) => {
-            println!();
-            println!("PEERNET");
-            println!("  PeerId:    {local_peer_id}");
-            println!("  Listening: {listening_on}");
-            println!("  Type 'help' for commands");
-            println!();
+    match args.command.take() {
+        #[cfg(unix)]
+        Some(Command::Ctl { socket, command }) => {
+            let socket = socket.map_or_else(daemon::default_socket_path, Ok)?;
+            ctl::run(&socket, command).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        #[cfg(unix)]
+        Some(Command::Daemon { socket }) => {
+            let socket = socket.map_or_else(daemon::default_socket_path, Ok)?;
+            run_daemon(args, &socket).await?;
+            Ok(ExitCode::SUCCESS)
         }
-        Some(other) => {
-            warn!(?other, "unexpected startup event");
+        Some(Command::Config {
+            command: ConfigCommand::Print,
+        }) => {
+            print!("{}", load_config(&args)?.to_toml());
+            Ok(ExitCode::SUCCESS)
         }
+        Some(Command::OneShot(command)) => run_oneshot(args, command).await,
         None => {
-            return Err(PeernetError::ChannelClosed {
-                actor: "network",
-                reason: "closed before startup",
-            });
+            run_interactive(args).await?;
+            Ok(ExitCode::SUCCESS)
         }
     }
+}
+
+async fn run_interactive(args: Args) -> PeernetResult<()> {
+    let cancel_token = CancellationToken::new();
+    spawn_signal_handler(cancel_token.clone());
+
+    let config = load_config(&args)?;
+    let output = args.output;
+    let metrics_addr = config.metrics_addr;
+    let bans_persisted = config.swarm.peer_lists.data_dir.is_some();
+    let (mut network, local_peer_id, listening_on) =
+        start_network(config, cancel_token.clone()).await?;
+
+    output.started(local_peer_id, &listening_on, metrics_addr);
 
     let (input_tx, input_rx) = mpsc::channel::<InputEvent>(32);
-    let input_handle = spawn_input_handler(cancel_token.clone(), input_tx);
+    let input_handle = spawn_input_handler(cancel_token.clone(), input_tx, output);
 
-    run_main_loop(&mut network, input_rx, cancel_token.clone()).await;
+    run_main_loop(
+        &mut network,
+        input_rx,
+        cancel_token.clone(),
+        output,
+        bans_persisted,
+    )
+    .await;
 
     let _ = input_handle.await;
     Ok(())
 }
 
+async fn run_oneshot(args: Args, command: oneshot::OneShotCommand) -> PeernetResult<ExitCode> {
+    let config = load_config(&args)?;
+    let output = args.output;
+    let wait = command.wait();
+    let operation = match command.operation() {
+        Ok(operation) => operation,
+        Err(e) => {
+            output.error(&e);
+            return Ok(oneshot::exit_code(&e));
+        }
+    };
+
+    let cancel_token = CancellationToken::new();
+    spawn_signal_handler(cancel_token.clone());
+
+    let (mut network, _, _) = start_network(config, cancel_token).await?;
+    let code = oneshot::run(&mut network, operation, wait, output).await;
+
+    let _ = network.shutdown().await;
+    while let Some(event) = network.recv().await {
+        if matches!(event, NetworkEvent::ShutdownComplete) {
+            break;
+        }
+    }
+    Ok(code)
+}
+
+#[cfg(unix)]
+async fn run_daemon(args: Args, socket: &std::path::Path) -> PeernetResult<()> {
+    let cancel_token = CancellationToken::new();
+    spawn_signal_handler(cancel_token.clone());
+
+    let config = load_config(&args)?;
+    let (mut network, peer_id, listen_addrs) = start_network(config, cancel_token.clone()).await?;
+    info!(%peer_id, ?listen_addrs, "network started");
+
+    let status = daemon::NodeStatus {
+        peer_id,
+        listen_addrs,
+    };
+    daemon::serve(&mut network, socket, status, cancel_token).await
+}
+
+// Defaults, then the config file, then PEERNET_* variables, then flags.
+fn load_config(args: &Args) -> PeernetResult<Config> {
+    let mut config = config::load(
+        args.config.as_deref(),
+        std::env::vars(),
+        args.psk.as_deref(),
+    )?;
+
+    if let Some(port) = args.port {
+        config.port = port;
+    }
+    if let Some(identity) = &args.identity {
+        config.identity = Some(identity.clone());
+    }
+    if let Some(data_dir) = &args.data_dir {
+        config.swarm.record_store.data_dir = Some(data_dir.clone());
+        config.swarm.peer_lists.data_dir = Some(data_dir.clone());
+    }
+    if args.no_quic {
+        config.transport.quic = false;
+    }
+    if let Some(port) = args.websocket_port {
+        config.transport.websocket = true;
+        config.transport.websocket_port = port;
+    }
+    if args.no_mdns {
+        config.swarm.enable_mdns = false;
+    }
+    if !args.bootstrap.is_empty() {
+        config.bootstrap_peers = args.bootstrap.clone();
+    }
+    if !args.relays.is_empty() {
+        config.relays = args.relays.clone();
+    }
+    if args.relay_server {
+        config.swarm.relay_server.enabled = true;
+    }
+    if let Some(addr) = args.metrics_addr {
+        config.metrics_addr = Some(addr);
+    }
+    Ok(config)
+}
+
+// Loads the key files the config points at and checks the result.
+fn network_config(config: &Config) -> PeernetResult<NetworkConfig> {
+    let mut network_config = config.to_network()?;
+    network_config.swarm.keypair = config
+        .identity
+        .as_deref()
+        .map(peernet_network::load_or_generate_identity)
+        .transpose()?;
+    network_config.swarm.psk = config
+        .psk
+        .as_deref()
+        .map(peernet_network::load_psk)
+        .transpose()?;
+    network_config.validate()?;
+    Ok(network_config)
+}
+
+async fn start_network(
+    config: Config,
+    cancel_token: CancellationToken,
+) -> PeernetResult<(NetworkHandle, PeerId, Vec<Multiaddr>)> {
+    let mut network = peernet_network::spawn(network_config(&config)?, cancel_token);
+
+    loop {
+        match network.recv().await {
+            Some(NetworkEvent::Started {
+                local_peer_id,
+                listening_on,
+            }) => return Ok((network, local_peer_id, listening_on)),
+            Some(other) => {
+                warn!(?other, "unexpected startup event");
+            }
+            None => {
+                return Err(PeernetError::ChannelClosed {
+                    actor: "network",
+                    reason: "closed before startup",
+                });
+            }
+        }
+    }
+}
+
+fn parse_bootstrap_addr(s: &str) -> Result<Multiaddr, String> {
+    let addr: Multiaddr = s.parse().map_err(|e| format!("{e}"))?;
+    if peernet_network::peer_id_of(&addr).is_none() {
+        return Err("expected a multiaddr ending in /p2p/<peer-id>".into());
+    }
+    Ok(addr)
+}
+
 fn init_tracing(verbose: u8) {
     let level = match verbose {
         0 => Level::INFO,
@@ -83,6 +359,7 @@ fn init_tracing(verbose: u8) {
         .add_directive("libp2p=warn".parse().unwrap());
 
     fmt()
+        .with_writer(io::stderr)
         .with_env_filter(filter)
         .with_target(false)
         .with_file(false)
@@ -92,16 +369,32 @@ fn init_tracing(verbose: u8) {
 
 fn spawn_signal_handler(cancel_token: CancellationToken) {
     tokio::spawn(async move {
-        let _ = tokio::signal::ctrl_c().await;
-        println!();
+        shutdown_signal().await;
+        eprintln!();
         info!("shutting down...");
         cancel_token.cancel();
     });
 }
 
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
 fn spawn_input_handler(
     cancel_token: CancellationToken,
     input_tx: mpsc::Sender<InputEvent>,
+    output: OutputFormat,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn_blocking(move || {
         let stdin = io::stdin();
@@ -112,8 +405,10 @@ fn spawn_input_handler(
                 break;
             }
 
-            let _ = write!(stdout, "peernet> ");
-            let _ = stdout.flush();
+            if !output.is_json() {
+                let _ = write!(stdout, "peernet> ");
+                let _ = stdout.flush();
+            }
 
             let mut line = String::new();
             match stdin.lock().read_line(&mut line) {
@@ -126,7 +421,7 @@ fn spawn_input_handler(
                     if input.is_empty() {
                         continue;
                     }
-                    if let Some(event) = handle_input(input, &cancel_token)
+                    if let Some(event) = handle_input(input, &cancel_token, output)
                         && input_tx.blocking_send(event).is_err()
                     {
                         break;
@@ -138,7 +433,11 @@ fn spawn_input_handler(
     })
 }
 
-fn handle_input(input: &str, cancel_token: &CancellationToken) -> Option<InputEvent> {
+fn handle_input(
+    input: &str,
+    cancel_token: &CancellationToken,
+    output: OutputFormat,
+) -> Option<InputEvent> {
     let parts: Vec<&str> = input.splitn(3, ' ').collect();
     let cmd = parts.first()?;
 
@@ -150,79 +449,295 @@ fn handle_input(input: &str, cancel_token: &CancellationToken) -> Option<InputEv
 
         "help" | "?" => {
             println!();
-            println!("  send <message>      broadcast message");
-            println!("  put <key> <value>   store in DHT");
-            println!("  get <key>           retrieve from DHT");
-            println!("  quit                exit");
+            println!("  send <message>          broadcast message");
+            println!("  send #<topic> <message> publish to a topic");
+            println!("  join <topic>            subscribe to a topic");
+            println!("  leave <topic>           unsubscribe from a topic");
+            println!("  topics                  list subscribed topics");
+            println!("  put <key> <value>       store in DHT");
+            println!("  put --ttl <s> <k> <v>   store in DHT for <s> seconds");
+            println!("  get <key>               retrieve from DHT");
+            println!("  ping <peer> [count]     print the next round trips to a peer");
+            println!("  peers                   list connected peers");
+            println!("  ban <peer>              disconnect and block a peer");
+            println!("  unban <peer>            lift a ban");
+            println!("  bans                    list banned peers");
+            println!("  dial <multiaddr|peer>   connect, looking a peer ID up in the DHT");
+            println!("  closest <key>           list the peers closest to a key");
+            println!("  quit                    exit");
             println!();
             None
         }
 
         "send" | "s" => {
             let msg = input.split_once(' ').map(|x| x.1).unwrap_or("");
+            let (topic, msg) = match msg.strip_prefix('#') {
+                Some(rest) => {
+                    let (name, msg) = rest.split_once(' ').unwrap_or((rest, ""));
+                    match TopicName::new(name) {
+                        Ok(topic) => (Some(topic), msg),
+                        Err(e) => {
+                            output.error(e);
+                            return None;
+                        }
+                    }
+                }
+                None => (None, msg),
+            };
             if msg.is_empty() {
-                println!("usage: send <message>");
+                output.usage("send [#<topic>] <message>");
                 return None;
             }
             match GossipPayload::from_text(msg) {
-                Ok(payload) => Some(InputEvent::Send(payload)),
+                Ok(payload) => Some(InputEvent::Send { topic, payload }),
                 Err(e) => {
-                    println!("error: {e}");
+                    output.error(e);
                     None
                 }
             }
         }
 
-        "put" => match (parts.get(1), parts.get(2)) {
-            (Some(k), Some(v)) => {
-                let key = match DhtKey::new(*k) {
-                    Ok(k) => k,
-                    Err(e) => {
-                        println!("error: {e}");
-                        return None;
-                    }
-                };
-                let value = match DhtValue::new(v.as_bytes().to_vec()) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        println!("error: {e}");
-                        return None;
-                    }
-                };
-                Some(InputEvent::Put { key, value })
-            }
-            _ => {
-                println!("usage: put <key> <value>");
+        "join" | "leave" => match parts.get(1) {
+            Some(name) => match TopicName::new(name.trim_start_matches('#')) {
+                Ok(topic) if *cmd == "join" => Some(InputEvent::Join(topic)),
+                Ok(topic) => Some(InputEvent::Leave(topic)),
+                Err(e) => {
+                    output.error(e);
+                    None
+                }
+            },
+            None => {
+                output.usage(format_args!("{cmd} <topic>"));
                 None
             }
         },
 
+        "topics" => Some(InputEvent::Topics),
+
+        "peers" => Some(InputEvent::Peers),
+
+        "put" => {
+            let args = input.split_once(' ').map(|x| x.1).unwrap_or("");
+            parse_put(args, output)
+        }
+
         "get" => match parts.get(1) {
             Some(k) => match DhtKey::new(*k) {
                 Ok(key) => Some(InputEvent::Get { key }),
                 Err(e) => {
-                    println!("error: {e}");
+                    output.error(e);
+                    None
+                }
+            },
+            None => {
+                output.usage("get <key>");
+                None
+            }
+        },
+
+        "ping" => {
+            let count = match parts.get(2) {
+                Some(count) => count.parse().ok().filter(|&n| n > 0),
+                None => Some(DEFAULT_PING_COUNT),
+            };
+            match (parts.get(1), count) {
+                (Some(peer), Some(count)) => Some(InputEvent::Ping {
+                    peer: peer.to_string(),
+                    count,
+                }),
+                _ => {
+                    output.usage("ping <peer> [count]");
+                    None
+                }
+            }
+        }
+
+        "ban" => match parts.get(1) {
+            Some(peer) => Some(InputEvent::Ban {
+                peer: peer.to_string(),
+            }),
+            None => {
+                output.usage("ban <peer>");
+                None
+            }
+        },
+
+        "unban" => match parts.get(1) {
+            Some(peer) => Some(InputEvent::Unban {
+                peer: peer.to_string(),
+            }),
+            None => {
+                output.usage("unban <peer>");
+                None
+            }
+        },
+
+        "bans" => Some(InputEvent::Bans),
+
+        "dial" => match parts.get(1) {
+            Some(target) => parse_dial(target, output),
+            None => {
+                output.usage("dial <multiaddr|peer-id>");
+                None
+            }
+        },
+
+        "closest" => match parts.get(1) {
+            Some(k) => match DhtKey::new(*k) {
+                Ok(key) => Some(InputEvent::Closest { key }),
+                Err(e) => {
+                    output.error(e);
                     None
                 }
             },
             None => {
-                println!("usage: get <key>");
+                output.usage("closest <key>");
                 None
             }
         },
 
         _ => {
-            println!("unknown command: {cmd}");
+            output.error(format_args!("unknown command: {cmd}"));
+            None
+        }
+    }
+}
+
+fn parse_put(args: &str, output: OutputFormat) -> Option<InputEvent> {
+    const USAGE: &str = "put [--ttl <seconds>] <key> <value>";
+
+    let (ttl, args) = match args.strip_prefix("--ttl ") {
+        Some(rest) => {
+            let Some((secs, rest)) = rest.split_once(' ') else {
+                output.usage(USAGE);
+                return None;
+            };
+            let Ok(secs) = secs.parse() else {
+                output.error(format_args!("invalid ttl: {secs}"));
+                return None;
+            };
+            (Some(Duration::from_secs(secs)), rest)
+        }
+        None => (None, args),
+    };
+
+    let Some((k, v)) = args.split_once(' ') else {
+        output.usage(USAGE);
+        return None;
+    };
+    let parsed = DhtKey::new(k).and_then(|key| Ok((key, DhtValue::new(v.as_bytes().to_vec())?)));
+    match parsed {
+        Ok((key, value)) => Some(InputEvent::Put { key, value, ttl }),
+        Err(e) => {
+            output.error(e);
+            None
+        }
+    }
+}
+
+// A bare peer ID has to be looked up in the DHT before it can be dialed.
+fn parse_dial(target: &str, output: OutputFormat) -> Option<InputEvent> {
+    if let Ok(peer_id) = target.parse() {
+        return Some(InputEvent::DialPeer { peer_id });
+    }
+    match target.parse() {
+        Ok(addr) => Some(InputEvent::Dial { addr }),
+        Err(e) => {
+            output.error(format_args!("not a peer ID or multiaddr: {target} ({e})"));
             None
         }
     }
 }
 
+// Peers are shown truncated, so accept any unambiguous prefix of a connected peer.
+async fn find_peer(network: &NetworkHandle, prefix: &str) -> Result<PeerId, String> {
+    let peers = network.connected_peers().await.map_err(|e| e.to_string())?;
+    match_peer(peers, prefix, "connected")
+}
+
+// A full peer ID is taken as is; anything else must be an unambiguous prefix
+// of one of `peers`, optionally with the "..." we print after truncated IDs.
+fn match_peer(peers: Vec<PeerId>, prefix: &str, kind: &str) -> Result<PeerId, String> {
+    if let Ok(peer_id) = prefix.parse() {
+        return Ok(peer_id);
+    }
+    let mut matches = peers
+        .into_iter()
+        .filter(|p| p.to_string().starts_with(prefix.trim_end_matches("...")));
+    match (matches.next(), matches.next()) {
+        (Some(peer_id), None) => Ok(peer_id),
+        (Some(_), Some(_)) => Err(format!("{prefix} matches more than one peer")),
+        (None, _) => Err(format!("no {kind} peer matches {prefix}")),
+    }
+}
+
+// A ban that silently disappears on restart is worse than none, so the
+// interactive command requires somewhere to keep it.
+async fn ban(network: &NetworkHandle, prefix: &str, persisted: bool) -> Result<(), String> {
+    if !persisted {
+        return Err("bans are only kept with --data-dir; restart with one to ban peers".into());
+    }
+    let peer_id = find_peer(network, prefix).await?;
+    network.block_peer(peer_id).await.map_err(|e| e.to_string())
+}
+
+async fn unban(network: &NetworkHandle, prefix: &str) -> Result<(), String> {
+    let banned = network.blocked_peers().await.map_err(|e| e.to_string())?;
+    let peer_id = match_peer(banned, prefix, "banned")?;
+    network
+        .unblock_peer(peer_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Failures only come back through the reply, so commands run in the
+// background print their own errors. Put and get are left to their
+// RecordStoreFailed and RecordNotFound events.
+fn spawn_reported<T: Send + 'static>(
+    reply: impl Future<Output = PeernetResult<T>> + Send + 'static,
+    output: OutputFormat,
+) {
+    tokio::spawn(async move {
+        if let Err(e) = reply.await {
+            output.error(e);
+        }
+    });
+}
+
+// Ping has no on-demand probe, so `ping` reports the next measurements the
+// periodic pinger makes rather than stored statistics.
+fn track_ping(pings: &mut HashMap<PeerId, (u32, u32)>, event: &NetworkEvent, output: OutputFormat) {
+    match event {
+        NetworkEvent::PeerLatency { peer_id, stats } => {
+            let Some((seen, count)) = pings.get_mut(peer_id) else {
+                return;
+            };
+            *seen += 1;
+            output.round_trip(*peer_id, stats.last, *seen, *count);
+            if seen == count {
+                pings.remove(peer_id);
+                output.latency(*peer_id, *stats);
+            }
+        }
+        NetworkEvent::PeerDisconnected { peer_id } => {
+            let waiting = pings.remove(peer_id).is_some();
+            if waiting {
+                output.error(format!("{peer_id} disconnected before the pings completed"));
+            }
+        }
+        _ => {}
+    }
+}
+
 async fn run_main_loop(
     network: &mut NetworkHandle,
     mut input_rx: mpsc::Receiver<InputEvent>,
     cancel_token: CancellationToken,
+    output: OutputFormat,
+    bans_persisted: bool,
 ) {
+    // Peers being watched by `ping`, with round trips seen and wanted.
+    let mut pings: HashMap<PeerId, (u32, u32)> = HashMap::new();
     loop {
         tokio::select! {
             () = cancel_token.cancelled() => {
@@ -232,64 +747,81 @@ async fn run_main_loop(
 
             Some(input) = input_rx.recv() => {
                 match input {
-                    InputEvent::Send(payload) => {
-                        let _ = network.publish(payload).await;
+                    InputEvent::Send { topic: Some(topic), payload } => {
+                        spawn_reported(network.publish_to(topic, payload), output);
                     }
-                    InputEvent::Put { key, value } => {
-                        let _ = network.put(key, value).await;
+                    InputEvent::Send { topic: None, payload } => {
+                        spawn_reported(network.publish(payload), output);
                     }
-                    InputEvent::Get { key } => {
-                        let _ = network.get(key).await;
+                    InputEvent::Join(topic) => {
+                        spawn_reported(network.subscribe(topic), output);
                     }
-                    InputEvent::Quit => {}
-                }
-            }
-
-            event = network.recv() => {
-                match event {
-                    Some(NetworkEvent::ShutdownComplete) => break,
-
-                    Some(NetworkEvent::PeerDiscovered { peer_id }) => {
-                        println!("[discovered] {}...", &peer_id.to_string()[..12]);
+                    InputEvent::Leave(topic) => {
+                        spawn_reported(network.unsubscribe(topic), output);
                     }
-                    Some(NetworkEvent::PeerConnected { peer_id }) => {
-                        println!("[connected] {}...", &peer_id.to_string()[..12]);
+                    InputEvent::Topics => match network.subscriptions().await {
+                        Ok(topics) => output.topics(&topics),
+                        Err(e) => output.error(e),
+                    },
+                    InputEvent::Put { key, value, ttl: None } => {
+                        tokio::spawn(network.put(key, value));
                     }
-                    Some(NetworkEvent::PeerDisconnected { peer_id }) => {
-                        println!("[disconnected] {}...", &peer_id.to_string()[..12]);
+                    InputEvent::Put { key, value, ttl: Some(ttl) } => {
+                        tokio::spawn(network.put_with_ttl(key, value, ttl));
                     }
-
-                    Some(NetworkEvent::GossipMessage { source, payload, .. }) => {
-                        let from = source
-                            .map(|p| format!("{}...", &p.to_string()[..12]))
-                            .unwrap_or_else(|| "unknown".into());
-                        let text = payload.as_str().unwrap_or("<binary>");
-                        println!("[message] {from}: {text}");
+                    InputEvent::Get { key } => {
+                        tokio::spawn(network.get(key));
                     }
-
-                    Some(NetworkEvent::RecordStored { key }) => {
-                        println!("[stored] {key}");
+                    InputEvent::Ping { peer, count } => match find_peer(network, &peer).await {
+                        Ok(peer_id) => {
+                            output.pinging(peer_id, count);
+                            pings.insert(peer_id, (0, count));
+                        }
+                        Err(e) => output.error(e),
+                    },
+                    InputEvent::Peers => match network.peers().await {
+                        Ok(peers) => output.peers(&peers),
+                        Err(e) => output.error(e),
+                    },
+                    InputEvent::Ban { peer } => {
+                        if let Err(e) = ban(network, &peer, bans_persisted).await {
+                            output.error(e);
+                        }
                     }
-                    Some(NetworkEvent::RecordStoreFailed { key, reason }) => {
-                        println!("[store failed] {key}: {reason}");
+                    InputEvent::Unban { peer } => {
+                        if let Err(e) = unban(network, &peer).await {
+                            output.error(e);
+                        }
                     }
-                    Some(NetworkEvent::RecordFound { key, value }) => {
-                        let text = std::str::from_utf8(value.as_bytes())
-                            .unwrap_or("<binary>");
-                        println!("[found] {key} = {text}");
+                    InputEvent::Bans => match network.blocked_peers().await {
+                        Ok(peers) => output.bans(&peers),
+                        Err(e) => output.error(e),
+                    },
+                    InputEvent::Dial { addr } => {
+                        if let Err(e) = network.dial(addr).await {
+                            output.error(e);
+                        }
                     }
-                    Some(NetworkEvent::RecordNotFound { key }) => {
-                        println!("[not found] {key}");
+                    InputEvent::DialPeer { peer_id } => {
+                        spawn_reported(network.find_peer(peer_id), output);
                     }
-
-                    Some(NetworkEvent::CommandFailed { reason }) => {
-                        println!("[error] {reason}");
+                    InputEvent::Closest { key } => {
+                        spawn_reported(network.closest_peers(key), output);
                     }
-
-                    Some(_) => {}
-                    None => break,
+                    InputEvent::Quit => {}
                 }
             }
+
+            event = network.recv() => match event {
+                Some(event) => {
+                    output.event(&event);
+                    track_ping(&mut pings, &event, output);
+                    if matches!(event, NetworkEvent::ShutdownComplete) {
+                        break;
+                    }
+                }
+                None => break,
+            },
         }
     }
 }
@@ -297,17 +829,325 @@ async fn run_main_loop(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
 
     #[test]
     fn cli_parses_defaults() {
         let args = Args::parse_from(["peernet"]);
-        assert_eq!(args.port, 0);
+        assert!(args.port.is_none());
+        assert!(args.config.is_none());
         assert_eq!(args.verbose, 0);
+        assert!(args.identity.is_none());
+        assert!(args.data_dir.is_none());
+        assert!(args.metrics_addr.is_none());
+        assert!(!args.no_quic);
+        assert!(!args.no_mdns);
+        assert!(args.bootstrap.is_empty());
     }
 
     #[test]
     fn cli_parses_port() {
         let args = Args::parse_from(["peernet", "-p", "4001"]);
-        assert_eq!(args.port, 4001);
+        assert_eq!(args.port, Some(4001));
+    }
+
+    #[test]
+    fn cli_parses_identity() {
+        let args = Args::parse_from(["peernet", "--identity", "node.key"]);
+        assert_eq!(args.identity, Some(PathBuf::from("node.key")));
+    }
+
+    #[test]
+    fn cli_parses_psk() {
+        let args = Args::parse_from(["peernet", "--psk", "swarm.key"]);
+        assert_eq!(args.psk, Some(PathBuf::from("swarm.key")));
+        assert_eq!(load_config(&args).unwrap().psk, args.psk);
+    }
+
+    #[test]
+    fn plain_psk_flag_starts_a_tcp_only_node() {
+        let path = std::env::temp_dir().join(format!("peernet-{}.key", std::process::id()));
+        std::fs::write(
+            &path,
+            format!("/key/swarm/psk/1.0.0/\n/base16/\n{}\n", "ab".repeat(32)),
+        )
+        .unwrap();
+
+        let args = Args::parse_from(["peernet".as_ref(), "--psk".as_ref(), path.as_os_str()]);
+        let result = load_config(&args).and_then(|config| network_config(&config));
+        std::fs::remove_file(&path).unwrap();
+
+        let network_config = result.unwrap();
+        assert!(network_config.swarm.psk.is_some());
+        assert!(network_config.transport.tcp);
+        assert!(!network_config.transport.quic);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_parses_daemon_and_ctl() {
+        let args = Args::parse_from([
+            "peernet",
+            "--port",
+            "4001",
+            "daemon",
+            "--socket",
+            "/tmp/p.sock",
+        ]);
+        assert_eq!(args.port, Some(4001));
+        assert!(matches!(
+            args.command,
+            Some(Command::Daemon { socket: Some(ref s) }) if s == Path::new("/tmp/p.sock")
+        ));
+
+        let args = Args::parse_from(["peernet", "ctl", "put", "k", "v", "--ttl", "30"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Ctl {
+                socket: None,
+                command: ctl::CtlCommand::Put { ttl: Some(30), .. },
+            })
+        ));
+    }
+
+    #[test]
+    fn cli_parses_oneshot_commands() {
+        let args = Args::parse_from(["peernet", "put", "k", "v", "--min-peers", "2"]);
+        match args.command {
+            Some(Command::OneShot(command)) => {
+                assert_eq!(command.wait().min_peers, 2);
+                assert_eq!(command.wait().timeout, 30);
+            }
+            other => panic!("expected one-shot put, got {other:?}"),
+        }
+
+        let args = Args::parse_from(["peernet", "publish", "#chat", "hi", "--timeout", "5"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::OneShot(oneshot::OneShotCommand::Publish { ref topic, .. })) if topic == "#chat"
+        ));
+        assert!(Args::try_parse_from(["peernet", "provide"]).is_err());
+    }
+
+    #[test]
+    fn flags_override_config() {
+        let args = Args::parse_from([
+            "peernet",
+            "--port",
+            "4001",
+            "--no-mdns",
+            "--data-dir",
+            "/var/lib/peernet",
+            "config",
+            "print",
+        ]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Config {
+                command: ConfigCommand::Print
+            })
+        ));
+
+        let config = load_config(&args).unwrap();
+        assert_eq!(config.port, 4001);
+        assert!(!config.swarm.enable_mdns);
+        assert!(config.transport.quic);
+        assert_eq!(
+            config.swarm.record_store.data_dir,
+            Some(PathBuf::from("/var/lib/peernet"))
+        );
+    }
+
+    #[test]
+    fn cli_websocket_port_enables_websocket() {
+        let config = load_config(&Args::parse_from(["peernet"])).unwrap();
+        assert!(!config.transport.websocket);
+
+        let args = Args::parse_from(["peernet", "--websocket-port", "8080"]);
+        let config = load_config(&args).unwrap();
+        assert!(config.transport.websocket);
+        assert_eq!(config.transport.websocket_port, 8080);
+    }
+
+    #[test]
+    fn cli_parses_metrics_addr() {
+        let args = Args::parse_from(["peernet", "--metrics-addr", "127.0.0.1:9090"]);
+        assert_eq!(args.metrics_addr, Some("127.0.0.1:9090".parse().unwrap()));
+    }
+
+    #[test]
+    fn cli_parses_output_format() {
+        assert_eq!(Args::parse_from(["peernet"]).output, OutputFormat::Text);
+        let args = Args::parse_from(["peernet", "--output", "json"]);
+        assert_eq!(args.output, OutputFormat::Json);
+        assert!(Args::try_parse_from(["peernet", "--output", "yaml"]).is_err());
+    }
+
+    #[test]
+    fn cli_parses_data_dir() {
+        let args = Args::parse_from(["peernet", "--data-dir", "/var/lib/peernet"]);
+        assert_eq!(args.data_dir, Some(PathBuf::from("/var/lib/peernet")));
+    }
+
+    #[test]
+    fn cli_parses_repeated_bootstrap() {
+        let peer = "12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN";
+        let args = Args::parse_from([
+            "peernet",
+            "--bootstrap",
+            &format!("/ip4/10.0.0.1/tcp/4001/p2p/{peer}"),
+            "--bootstrap",
+            &format!("/dns4/boot.example/tcp/4001/p2p/{peer}"),
+        ]);
+        assert_eq!(args.bootstrap.len(), 2);
+    }
+
+    #[test]
+    fn cli_parses_relay_flags() {
+        let relay =
+            "/ip4/10.0.0.1/tcp/4001/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN";
+        let args = Args::parse_from(["peernet", "--relay", relay, "--relay-server"]);
+        let config = load_config(&args).unwrap();
+        assert_eq!(config.relays, vec![relay.parse::<Multiaddr>().unwrap()]);
+        assert!(config.swarm.relay_server.enabled);
+
+        let result = Args::try_parse_from(["peernet", "--relay", "/ip4/10.0.0.1/tcp/4001"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cli_rejects_bootstrap_without_peer_id() {
+        let result = Args::try_parse_from(["peernet", "--bootstrap", "/ip4/10.0.0.1/tcp/4001"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn send_parses_topic() {
+        let cancel = CancellationToken::new();
+        match handle_input("send #chat hello there", &cancel, OutputFormat::Text) {
+            Some(InputEvent::Send {
+                topic: Some(topic),
+                payload,
+            }) => {
+                assert_eq!(topic.as_str(), "chat");
+                assert_eq!(payload.as_str(), Some("hello there"));
+            }
+            other => panic!("expected topic send, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn send_defaults_topic() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("send hello", &cancel, OutputFormat::Text),
+            Some(InputEvent::Send { topic: None, .. })
+        ));
+        assert!(handle_input("send #chat", &cancel, OutputFormat::Text).is_none());
+    }
+
+    #[test]
+    fn join_and_leave_parse_topic() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("join #chat", &cancel, OutputFormat::Text),
+            Some(InputEvent::Join(t)) if t.as_str() == "chat"
+        ));
+        assert!(matches!(
+            handle_input("leave chat", &cancel, OutputFormat::Text),
+            Some(InputEvent::Leave(t)) if t.as_str() == "chat"
+        ));
+    }
+
+    #[test]
+    fn ping_requires_a_peer() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("ping 12D3KooWabc", &cancel, OutputFormat::Text),
+            Some(InputEvent::Ping { ref peer, count: DEFAULT_PING_COUNT }) if peer == "12D3KooWabc"
+        ));
+        assert!(matches!(
+            handle_input("ping 12D3KooWabc 5", &cancel, OutputFormat::Text),
+            Some(InputEvent::Ping { count: 5, .. })
+        ));
+        assert!(handle_input("ping", &cancel, OutputFormat::Text).is_none());
+        assert!(handle_input("ping 12D3KooWabc 0", &cancel, OutputFormat::Text).is_none());
+    }
+
+    #[test]
+    fn ban_and_unban_require_a_peer() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("ban 12D3KooWabc...", &cancel, OutputFormat::Text),
+            Some(InputEvent::Ban { ref peer }) if peer == "12D3KooWabc..."
+        ));
+        assert!(matches!(
+            handle_input("unban 12D3KooWabc", &cancel, OutputFormat::Text),
+            Some(InputEvent::Unban { ref peer }) if peer == "12D3KooWabc"
+        ));
+        assert!(handle_input("ban", &cancel, OutputFormat::Text).is_none());
+        assert!(matches!(
+            handle_input("bans", &cancel, OutputFormat::Text),
+            Some(InputEvent::Bans)
+        ));
+    }
+
+    #[test]
+    fn dial_accepts_peer_ids_and_multiaddrs() {
+        let cancel = CancellationToken::new();
+        let peer_id = PeerId::random();
+        assert!(matches!(
+            handle_input(&format!("dial {peer_id}"), &cancel, OutputFormat::Text),
+            Some(InputEvent::DialPeer { peer_id: p }) if p == peer_id
+        ));
+        assert!(matches!(
+            handle_input("dial /ip4/10.0.0.1/tcp/4001", &cancel, OutputFormat::Text),
+            Some(InputEvent::Dial { ref addr }) if addr.to_string() == "/ip4/10.0.0.1/tcp/4001"
+        ));
+        assert!(handle_input("dial somewhere", &cancel, OutputFormat::Text).is_none());
+        assert!(handle_input("dial", &cancel, OutputFormat::Text).is_none());
+        assert!(matches!(
+            handle_input("closest greeting", &cancel, OutputFormat::Text),
+            Some(InputEvent::Closest { ref key }) if key.as_str() == "greeting"
+        ));
+    }
+
+    #[test]
+    fn matches_full_ids_and_unambiguous_prefixes() {
+        let peers = vec![PeerId::random(), PeerId::random()];
+        let full = peers[0].to_string();
+
+        assert_eq!(match_peer(Vec::new(), &full, "banned"), Ok(peers[0]));
+        assert_eq!(
+            match_peer(peers.clone(), &format!("{}...", &full[..40]), "banned"),
+            Ok(peers[0])
+        );
+        assert!(match_peer(peers.clone(), "12D3KooW", "banned").is_err());
+        assert!(match_peer(peers, "nope", "banned").is_err());
+    }
+
+    #[test]
+    fn peers_takes_no_arguments() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("peers", &cancel, OutputFormat::Text),
+            Some(InputEvent::Peers)
+        ));
+    }
+
+    #[test]
+    fn put_parses_optional_ttl() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            handle_input("put k some value", &cancel, OutputFormat::Text),
+            Some(InputEvent::Put { ttl: None, ref value, .. }) if value.as_bytes() == b"some value"
+        ));
+        assert!(matches!(
+            handle_input("put --ttl 30 k v", &cancel, OutputFormat::Text),
+            Some(InputEvent::Put { ttl: Some(ttl), ref key, .. })
+                if ttl == Duration::from_secs(30) && key.as_str() == "k"
+        ));
+        assert!(handle_input("put --ttl soon k v", &cancel, OutputFormat::Text).is_none());
+        assert!(handle_input("put k", &cancel, OutputFormat::Text).is_none());
     }
 }
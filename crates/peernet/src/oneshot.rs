@@ -0,0 +1,215 @@
+use crate::output::OutputFormat;
+use clap::{Args, Subcommand};
+use peernet_core::{
+    CommandError, DhtKey, DhtValue, GossipPayload, NetworkEvent, PeerId, PeernetError,
+    PeernetResult, TopicName,
+};
+use peernet_network::{NetworkHandle, PutOptions};
+use std::{collections::HashSet, process::ExitCode, time::Duration};
+
+pub const EXIT_FAILED: u8 = 1;
+pub const EXIT_INVALID: u8 = 2;
+pub const EXIT_NOT_FOUND: u8 = 3;
+pub const EXIT_TIMED_OUT: u8 = 4;
+
+// Publishing only queues the message on the connection; give it a moment to
+// leave before the node shuts down.
+const PUBLISH_LINGER: Duration = Duration::from_millis(500);
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct WaitArgs {
+    #[arg(long, default_value_t = 1)]
+    pub min_peers: usize,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    pub timeout: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OneShotCommand {
+    Put {
+        key: String,
+        value: String,
+        #[arg(long, value_name = "SECONDS")]
+        ttl: Option<u64>,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    Get {
+        key: String,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    Publish {
+        topic: String,
+        message: String,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    Provide {
+        key: String,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+}
+
+#[derive(Debug)]
+pub enum Operation {
+    Put {
+        key: DhtKey,
+        value: DhtValue,
+        ttl: Option<Duration>,
+    },
+    Get {
+        key: DhtKey,
+    },
+    Publish {
+        topic: TopicName,
+        payload: GossipPayload,
+    },
+    Provide {
+        key: DhtKey,
+    },
+}
+
+impl OneShotCommand {
+    pub fn wait(&self) -> WaitArgs {
+        match self {
+            Self::Put { wait, .. }
+            | Self::Get { wait, .. }
+            | Self::Publish { wait, .. }
+            | Self::Provide { wait, .. } => *wait,
+        }
+    }
+
+    pub fn operation(self) -> PeernetResult<Operation> {
+        Ok(match self {
+            Self::Put {
+                key, value, ttl, ..
+            } => Operation::Put {
+                key: DhtKey::new(key)?,
+                value: DhtValue::new(value.into_bytes())?,
+                ttl: ttl.map(Duration::from_secs),
+            },
+            Self::Get { key, .. } => Operation::Get {
+                key: DhtKey::new(key)?,
+            },
+            Self::Publish { topic, message, .. } => Operation::Publish {
+                topic: TopicName::new(topic.trim_start_matches('#'))?,
+                payload: GossipPayload::from_text(&message)?,
+            },
+            Self::Provide { key, .. } => Operation::Provide {
+                key: DhtKey::new(key)?,
+            },
+        })
+    }
+}
+
+pub async fn run(
+    network: &mut NetworkHandle,
+    operation: Operation,
+    wait: WaitArgs,
+    output: OutputFormat,
+) -> ExitCode {
+    let timeout = Duration::from_secs(wait.timeout);
+    match tokio::time::timeout(timeout, execute(network, operation, wait.min_peers, output)).await {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        Ok(Err(e)) => {
+            output.error(&e);
+            exit_code(&e)
+        }
+        Err(_) => {
+            output.error(format_args!("timed out after {}s", wait.timeout));
+            ExitCode::from(EXIT_TIMED_OUT)
+        }
+    }
+}
+
+pub fn exit_code(error: &PeernetError) -> ExitCode {
+    match error {
+        PeernetError::ValidationFailed { .. } => ExitCode::from(EXIT_INVALID),
+        PeernetError::Command(CommandError::RecordNotFound { .. }) => {
+            ExitCode::from(EXIT_NOT_FOUND)
+        }
+        _ => ExitCode::from(EXIT_FAILED),
+    }
+}
+
+async fn execute(
+    network: &mut NetworkHandle,
+    operation: Operation,
+    min_peers: usize,
+    output: OutputFormat,
+) -> PeernetResult<()> {
+    match operation {
+        Operation::Put { key, value, ttl } => {
+            wait_for_peers(network, min_peers, None).await?;
+            let options = PutOptions {
+                ttl,
+                ..Default::default()
+            };
+            network.put_with(key.clone(), value, options).await?;
+            output.event(&NetworkEvent::RecordStored { key });
+        }
+
+        Operation::Get { key } => {
+            wait_for_peers(network, min_peers, None).await?;
+            let value = network.get(key.clone()).await?;
+            output.value(key, value);
+        }
+
+        Operation::Publish { topic, payload } => {
+            network.subscribe(topic.clone()).await?;
+            wait_for_peers(network, min_peers, Some(&topic)).await?;
+            network.publish_to(topic.clone(), payload).await?;
+            tokio::time::sleep(PUBLISH_LINGER).await;
+            output.published(&topic);
+        }
+
+        Operation::Provide { key } => {
+            wait_for_peers(network, min_peers, None).await?;
+            network.start_providing(key.clone()).await?;
+            output.event(&NetworkEvent::ProviderRecordStored { key });
+        }
+    }
+    Ok(())
+}
+
+// With a topic, only peers subscribed to it count; otherwise any connection does.
+async fn wait_for_peers(
+    network: &mut NetworkHandle,
+    min_peers: usize,
+    topic: Option<&TopicName>,
+) -> PeernetResult<()> {
+    let mut peers: HashSet<PeerId> = HashSet::new();
+    while peers.len() < min_peers {
+        match network.recv().await {
+            Some(NetworkEvent::PeerConnected { peer_id }) if topic.is_none() => {
+                peers.insert(peer_id);
+            }
+            Some(NetworkEvent::PeerSubscribed {
+                peer_id,
+                topic: subscribed,
+            }) if Some(&subscribed) == topic => {
+                peers.insert(peer_id);
+            }
+            Some(NetworkEvent::PeerUnsubscribed {
+                peer_id,
+                topic: unsubscribed,
+            }) if Some(&unsubscribed) == topic => {
+                peers.remove(&peer_id);
+            }
+            Some(NetworkEvent::PeerDisconnected { peer_id }) => {
+                peers.remove(&peer_id);
+            }
+            Some(NetworkEvent::ShutdownComplete) | None => {
+                return Err(PeernetError::ChannelClosed {
+                    actor: "network",
+                    reason: "shut down while waiting for peers",
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
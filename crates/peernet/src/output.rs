@@ -0,0 +1,419 @@
+use crate::rpc;
+use clap::ValueEnum;
+use peernet_core::{
+    DhtKey, DhtValue, LatencyStats, Multiaddr, NetworkEvent, PeerId, PeerInfo, TopicName,
+};
+use serde_json::{Value, json};
+use std::{
+    fmt::Display,
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    pub fn started(
+        self,
+        local_peer_id: PeerId,
+        listening_on: &[Multiaddr],
+        metrics_addr: Option<SocketAddr>,
+    ) {
+        if self.is_json() {
+            let mut started = rpc::encode_event(&NetworkEvent::Started {
+                local_peer_id,
+                listening_on: listening_on.to_vec(),
+            });
+            if let Some(addr) = metrics_addr {
+                started["metrics_addr"] = addr.to_string().into();
+            }
+            print_json(started);
+            return;
+        }
+
+        println!();
+        println!("PEERNET");
+        println!("  PeerId:    {local_peer_id}");
+        for (i, addr) in listening_on.iter().enumerate() {
+            let label = if i == 0 { "Listening:" } else { "" };
+            println!("  {label:<10} {addr}");
+        }
+        if let Some(addr) = metrics_addr {
+            println!("  Metrics:   http://{addr}/metrics");
+        }
+        println!("  Type 'help' for commands");
+        println!();
+    }
+
+    pub fn event(self, event: &NetworkEvent) {
+        if self.is_json() {
+            print_json(rpc::encode_event(event));
+        } else {
+            print_text_event(event);
+        }
+    }
+
+    pub fn topics(self, topics: &[TopicName]) {
+        if self.is_json() {
+            let topics: Vec<_> = topics.iter().map(|t| t.as_str()).collect();
+            print_json(json!({ "type": "topics", "topics": topics }));
+        } else {
+            for topic in topics {
+                println!("  #{topic}");
+            }
+        }
+    }
+
+    pub fn value(self, key: DhtKey, value: DhtValue) {
+        if self.is_json() {
+            print_json(rpc::encode_event(&NetworkEvent::RecordFound { key, value }));
+        } else {
+            println!("{}", String::from_utf8_lossy(value.as_bytes()));
+        }
+    }
+
+    pub fn published(self, topic: &TopicName) {
+        if self.is_json() {
+            print_json(json!({ "type": "published", "topic": topic.as_str() }));
+        } else {
+            println!("[published] #{topic}");
+        }
+    }
+
+    pub fn pinging(self, peer_id: PeerId, count: u32) {
+        if !self.is_json() {
+            let peer = &peer_id.to_string()[..12];
+            println!("[ping] waiting for {count} round trips to {peer}...");
+        }
+    }
+
+    pub fn round_trip(self, peer_id: PeerId, rtt: Duration, seq: u32, count: u32) {
+        if self.is_json() {
+            print_json(json!({
+                "type": "round_trip",
+                "peer_id": peer_id.to_string(),
+                "rtt_ms": rtt.as_secs_f64() * 1000.0,
+                "seq": seq,
+                "count": count,
+            }));
+            return;
+        }
+
+        let peer = &peer_id.to_string()[..12];
+        println!("[ping] {peer}... {rtt:.2?} ({seq}/{count})");
+    }
+
+    pub fn latency(self, peer_id: PeerId, stats: LatencyStats) {
+        if self.is_json() {
+            let mut latency = rpc::encode_latency(&stats);
+            latency["type"] = "latency".into();
+            latency["peer_id"] = peer_id.to_string().into();
+            print_json(latency);
+            return;
+        }
+
+        let peer = &peer_id.to_string()[..12];
+        println!(
+            "[ping] {peer}... last {:.2?} avg {:.2?} min {:.2?} over {} pings",
+            stats.last, stats.average, stats.min, stats.samples
+        );
+    }
+
+    pub fn peers(self, peers: &[PeerInfo]) {
+        if self.is_json() {
+            let peers: Vec<_> = peers.iter().map(encode_peer).collect();
+            print_json(json!({ "type": "peers", "peers": peers }));
+            return;
+        }
+
+        if peers.is_empty() {
+            println!("  no peers connected");
+            return;
+        }
+        println!(
+            "  {:<15}  {:<8}  {:>5}  {:>8}  {:>9}  {:<20}  TOPICS",
+            "PEER", "DIR", "CONNS", "SINCE", "RTT", "AGENT"
+        );
+        for info in peers {
+            let since = SystemTime::now()
+                .duration_since(info.connected_since)
+                .unwrap_or_default();
+            let rtt = info
+                .latency
+                .map(|stats| format!("{:.1?}", stats.average))
+                .unwrap_or_else(|| "-".to_string());
+            let topics: Vec<_> = info.topics.iter().map(|t| format!("#{t}")).collect();
+            println!(
+                "  {:<15}  {:<8}  {:>5}  {:>8}  {:>9}  {:<20}  {}",
+                format!("{}...", &info.peer_id.to_string()[..12]),
+                info.direction,
+                info.connections,
+                elapsed(since),
+                rtt,
+                info.agent_version.as_deref().unwrap_or("-"),
+                topics.join(" ")
+            );
+        }
+    }
+
+    pub fn bans(self, peers: &[PeerId]) {
+        if self.is_json() {
+            let peers: Vec<_> = peers.iter().map(|p| p.to_string()).collect();
+            print_json(json!({ "type": "bans", "peers": peers }));
+        } else if peers.is_empty() {
+            println!("  no banned peers");
+        } else {
+            for peer in peers {
+                println!("  {peer}");
+            }
+        }
+    }
+
+    pub fn error(self, message: impl Display) {
+        if self.is_json() {
+            print_json(json!({ "type": "error", "message": message.to_string() }));
+        } else {
+            println!("error: {message}");
+        }
+    }
+
+    pub fn usage(self, usage: impl Display) {
+        if self.is_json() {
+            print_json(json!({ "type": "error", "message": format!("usage: {usage}") }));
+        } else {
+            println!("usage: {usage}");
+        }
+    }
+}
+
+// Also the daemon's `peers` result, so both print the same shape.
+pub(crate) fn encode_peer(info: &PeerInfo) -> Value {
+    let since_epoch = info
+        .connected_since
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let addresses: Vec<_> = info.addresses.iter().map(|a| a.to_string()).collect();
+    let topics: Vec<_> = info.topics.iter().map(|t| t.as_str()).collect();
+    json!({
+        "peer_id": info.peer_id.to_string(),
+        "addresses": addresses,
+        "direction": info.direction.to_string(),
+        "connections": info.connections,
+        "connected_since": rfc3339(since_epoch),
+        "agent_version": info.agent_version,
+        "protocols": info.protocols,
+        "topics": topics,
+        "latency": info.latency.as_ref().map(rpc::encode_latency),
+    })
+}
+
+fn elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn print_json(mut value: Value) {
+    if let Value::Object(fields) = &mut value {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        fields.insert("timestamp".into(), rfc3339(since_epoch).into());
+    }
+    println!("{value}");
+}
+
+fn print_text_event(event: &NetworkEvent) {
+    match event {
+        NetworkEvent::Listening { address } => {
+            println!("[listening] {address}");
+        }
+        NetworkEvent::PeerDiscovered { peer_id } => {
+            println!("[discovered] {}...", &peer_id.to_string()[..12]);
+        }
+        NetworkEvent::PeerConnected { peer_id } => {
+            println!("[connected] {}...", &peer_id.to_string()[..12]);
+        }
+        NetworkEvent::PeerDisconnected { peer_id } => {
+            println!("[disconnected] {}...", &peer_id.to_string()[..12]);
+        }
+        NetworkEvent::PeerIdentified {
+            peer_id,
+            agent_version,
+            ..
+        } => {
+            println!(
+                "[identified] {}... {agent_version}",
+                &peer_id.to_string()[..12]
+            );
+        }
+        NetworkEvent::PeerBlocked { peer_id } => {
+            println!("[banned] {}...", &peer_id.to_string()[..12]);
+        }
+        NetworkEvent::PeerUnblocked { peer_id } => {
+            println!("[unbanned] {}...", &peer_id.to_string()[..12]);
+        }
+        NetworkEvent::RelayReservationAccepted {
+            relay_peer_id,
+            renewal: false,
+        } => {
+            println!(
+                "[relay] reserved a slot on {}...",
+                &relay_peer_id.to_string()[..12]
+            );
+        }
+        NetworkEvent::RelayedConnection {
+            peer_id,
+            relay_peer_id,
+        } => {
+            println!(
+                "[relayed] {}... via {}...",
+                &peer_id.to_string()[..12],
+                &relay_peer_id.to_string()[..12]
+            );
+        }
+        NetworkEvent::ReachabilityChanged {
+            reachability,
+            dht_mode,
+        } => {
+            println!("[nat] {reachability}, DHT {dht_mode} mode");
+        }
+        NetworkEvent::ConnectionLimitReached { reason, .. } => {
+            println!("[limit] {reason}");
+        }
+
+        NetworkEvent::GossipMessage {
+            source,
+            topic,
+            payload,
+        } => {
+            let from = source
+                .map(|p| format!("{}...", &p.to_string()[..12]))
+                .unwrap_or_else(|| "unknown".into());
+            let text = payload.as_str().unwrap_or("<binary>");
+            println!("[message] #{topic} {from}: {text}");
+        }
+        NetworkEvent::Subscribed { topic } => {
+            println!("[joined] #{topic}");
+        }
+        NetworkEvent::Unsubscribed { topic } => {
+            println!("[left] #{topic}");
+        }
+
+        NetworkEvent::RecordStored { key } => {
+            println!("[stored] {key}");
+        }
+        NetworkEvent::RecordStoreFailed { key, reason } => {
+            println!("[store failed] {key}: {reason}");
+        }
+        NetworkEvent::RecordFound { key, value } => {
+            let text = std::str::from_utf8(value.as_bytes()).unwrap_or("<binary>");
+            println!("[found] {key} = {text}");
+        }
+        NetworkEvent::RecordNotFound { key } => {
+            println!("[not found] {key}");
+        }
+        NetworkEvent::RecordExpired { key } => {
+            println!("[expired] {key}");
+        }
+        NetworkEvent::RecordPersistFailed { reason } => {
+            println!("[persist failed] {reason}");
+        }
+        NetworkEvent::ProviderRecordStored { key } => {
+            println!("[providing] {key}");
+        }
+        NetworkEvent::PeerFound { peer_id, addresses } => {
+            let addresses: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+            println!(
+                "[located] {}... at {}",
+                &peer_id.to_string()[..12],
+                addresses.join(", ")
+            );
+        }
+        NetworkEvent::ClosestPeers { key, peers } if peers.is_empty() => {
+            println!("[closest] {key}: no peers known");
+        }
+        NetworkEvent::ClosestPeers { key, peers } => {
+            let peers: Vec<String> = peers
+                .iter()
+                .map(|p| format!("{}...", &p.to_string()[..12]))
+                .collect();
+            println!("[closest] {key}: {}", peers.join(", "));
+        }
+
+        NetworkEvent::BootstrapCompleted => {
+            println!("[bootstrap] completed");
+        }
+        NetworkEvent::BootstrapFailed { reason } => {
+            println!("[bootstrap failed] {reason}");
+        }
+
+        NetworkEvent::CommandFailed { reason } => {
+            println!("[error] {reason}");
+        }
+
+        _ => {}
+    }
+}
+
+// Civil-from-days conversion so timestamps don't need a date/time dependency.
+fn rfc3339(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rfc3339_timestamps() {
+        assert_eq!(rfc3339(Duration::ZERO), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339(Duration::from_millis(1_700_000_000_250)),
+            "2023-11-14T22:13:20.250Z"
+        );
+        assert_eq!(
+            rfc3339(Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn formats_elapsed_durations() {
+        assert_eq!(elapsed(Duration::from_secs(42)), "42s");
+        assert_eq!(elapsed(Duration::from_secs(192)), "3m12s");
+        assert_eq!(elapsed(Duration::from_secs(7_500)), "2h05m");
+    }
+}
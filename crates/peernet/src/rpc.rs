@@ -0,0 +1,392 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use peernet_core::{LatencyStats, NetworkEvent, PeernetError, Reachability};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const COMMAND_FAILED: i64 = -32000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            id: id.into(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".into(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+impl Notification {
+    pub fn event(event: &NetworkEvent) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            method: "event".into(),
+            params: encode_event(event),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn parse(e: impl ToString) -> Self {
+        Self {
+            code: PARSE_ERROR,
+            message: e.to_string(),
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown method: {method}"),
+        }
+    }
+
+    pub fn invalid_params(e: impl ToString) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: e.to_string(),
+        }
+    }
+
+    pub fn command_failed(e: PeernetError) -> Self {
+        Self {
+            code: COMMAND_FAILED,
+            message: e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyParams {
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PutParams {
+    pub key: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    pub payload: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopicParams {
+    pub topic: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EventsParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+}
+
+pub fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+pub fn encode_bytes(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+pub fn decode_bytes(encoded: &str) -> Result<Vec<u8>, RpcError> {
+    STANDARD.decode(encoded).map_err(RpcError::invalid_params)
+}
+
+pub fn encode_event(event: &NetworkEvent) -> Value {
+    match event {
+        NetworkEvent::Started {
+            local_peer_id,
+            listening_on,
+        } => json!({
+            "type": "started",
+            "local_peer_id": local_peer_id.to_string(),
+            "listening_on": listening_on.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+        }),
+        NetworkEvent::ShutdownComplete => json!({ "type": "shutdown_complete" }),
+        NetworkEvent::PeerDiscovered { peer_id } => {
+            json!({ "type": "peer_discovered", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerConnected { peer_id } => {
+            json!({ "type": "peer_connected", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerDisconnected { peer_id } => {
+            json!({ "type": "peer_disconnected", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerIdentified {
+            peer_id,
+            agent_version,
+            protocol_version,
+            protocols,
+            listen_addrs,
+            observed_addr,
+        } => json!({
+            "type": "peer_identified",
+            "peer_id": peer_id.to_string(),
+            "agent_version": agent_version,
+            "protocol_version": protocol_version,
+            "protocols": protocols,
+            "listen_addrs": listen_addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            "observed_addr": observed_addr.to_string(),
+        }),
+        NetworkEvent::PeerLatency { peer_id, stats } => {
+            let mut encoded = encode_latency(stats);
+            encoded["type"] = "peer_latency".into();
+            encoded["peer_id"] = peer_id.to_string().into();
+            encoded
+        }
+        NetworkEvent::PeerBlocked { peer_id } => {
+            json!({ "type": "peer_blocked", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerUnblocked { peer_id } => {
+            json!({ "type": "peer_unblocked", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerAllowed { peer_id } => {
+            json!({ "type": "peer_allowed", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::PeerDisallowed { peer_id } => {
+            json!({ "type": "peer_disallowed", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::RelayReservationAccepted {
+            relay_peer_id,
+            renewal,
+        } => json!({
+            "type": "relay_reservation_accepted",
+            "relay_peer_id": relay_peer_id.to_string(),
+            "renewal": renewal,
+        }),
+        NetworkEvent::RelayReservationGranted { peer_id } => {
+            json!({ "type": "relay_reservation_granted", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::RelayCircuitOpened {
+            src_peer_id,
+            dst_peer_id,
+        } => json!({
+            "type": "relay_circuit_opened",
+            "src_peer_id": src_peer_id.to_string(),
+            "dst_peer_id": dst_peer_id.to_string(),
+        }),
+        NetworkEvent::RelayedConnection {
+            peer_id,
+            relay_peer_id,
+        } => json!({
+            "type": "relayed_connection",
+            "peer_id": peer_id.to_string(),
+            "relay_peer_id": relay_peer_id.to_string(),
+        }),
+        NetworkEvent::ReachabilityChanged {
+            reachability,
+            dht_mode,
+        } => {
+            let (status, address) = match reachability {
+                Reachability::Unknown => ("unknown", None),
+                Reachability::Public(address) => ("public", Some(address.to_string())),
+                Reachability::Private => ("private", None),
+            };
+            json!({
+                "type": "reachability_changed",
+                "reachability": status,
+                "address": address,
+                "dht_mode": dht_mode.to_string(),
+            })
+        }
+        NetworkEvent::ConnectionLimitReached {
+            peer_id,
+            direction,
+            limit,
+            reason,
+        } => json!({
+            "type": "connection_limit_reached",
+            "peer_id": peer_id.map(|p| p.to_string()),
+            "direction": direction.to_string(),
+            "limit": limit,
+            "reason": reason,
+        }),
+        NetworkEvent::Listening { address } => {
+            json!({ "type": "listening", "address": address.to_string() })
+        }
+        NetworkEvent::GossipMessage {
+            source,
+            topic,
+            payload,
+        } => json!({
+            "type": "gossip_message",
+            "source": source.map(|p| p.to_string()),
+            "topic": topic.as_str(),
+            "payload": encode_bytes(payload.as_bytes()),
+        }),
+        NetworkEvent::Subscribed { topic } => {
+            json!({ "type": "subscribed", "topic": topic.as_str() })
+        }
+        NetworkEvent::Unsubscribed { topic } => {
+            json!({ "type": "unsubscribed", "topic": topic.as_str() })
+        }
+        NetworkEvent::PeerSubscribed { peer_id, topic } => json!({
+            "type": "peer_subscribed",
+            "peer_id": peer_id.to_string(),
+            "topic": topic.as_str(),
+        }),
+        NetworkEvent::PeerUnsubscribed { peer_id, topic } => json!({
+            "type": "peer_unsubscribed",
+            "peer_id": peer_id.to_string(),
+            "topic": topic.as_str(),
+        }),
+        NetworkEvent::RecordStored { key } => {
+            json!({ "type": "record_stored", "key": key.as_str() })
+        }
+        NetworkEvent::RecordStoreFailed { key, reason } => json!({
+            "type": "record_store_failed",
+            "key": key.as_str(),
+            "reason": reason,
+        }),
+        NetworkEvent::RecordFound { key, value } => json!({
+            "type": "record_found",
+            "key": key.as_str(),
+            "value": encode_bytes(value.as_bytes()),
+        }),
+        NetworkEvent::RecordNotFound { key } => {
+            json!({ "type": "record_not_found", "key": key.as_str() })
+        }
+        NetworkEvent::RecordExpired { key } => {
+            json!({ "type": "record_expired", "key": key.as_str() })
+        }
+        NetworkEvent::RecordPersistFailed { reason } => {
+            json!({ "type": "record_persist_failed", "reason": reason })
+        }
+        NetworkEvent::ProviderRecordStored { key } => {
+            json!({ "type": "provider_record_stored", "key": key.as_str() })
+        }
+        NetworkEvent::ProvidersFound { key, providers } => json!({
+            "type": "providers_found",
+            "key": key.as_str(),
+            "providers": providers.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        }),
+        NetworkEvent::PeerFound { peer_id, addresses } => json!({
+            "type": "peer_found",
+            "peer_id": peer_id.to_string(),
+            "addresses": addresses.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+        }),
+        NetworkEvent::ClosestPeers { key, peers } => json!({
+            "type": "closest_peers",
+            "key": key.as_str(),
+            "peers": peers.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+        }),
+        NetworkEvent::RoutingUpdated { peer_id } => {
+            json!({ "type": "routing_updated", "peer_id": peer_id.to_string() })
+        }
+        NetworkEvent::BootstrapCompleted => json!({ "type": "bootstrap_completed" }),
+        NetworkEvent::BootstrapFailed { reason } => {
+            json!({ "type": "bootstrap_failed", "reason": reason })
+        }
+        NetworkEvent::CommandFailed { reason } => {
+            json!({ "type": "command_failed", "reason": reason })
+        }
+    }
+}
+
+pub fn encode_latency(stats: &LatencyStats) -> Value {
+    json!({
+        "last_ms": stats.last.as_secs_f64() * 1000.0,
+        "average_ms": stats.average.as_secs_f64() * 1000.0,
+        "min_ms": stats.min.as_secs_f64() * 1000.0,
+        "samples": stats.samples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peernet_core::{GossipPayload, PeerId, TopicName};
+
+    #[test]
+    fn encodes_gossip_payload_as_base64() {
+        let source = PeerId::random();
+        let event = NetworkEvent::GossipMessage {
+            source: Some(source),
+            topic: TopicName::new_unchecked("chat"),
+            payload: GossipPayload::new(vec![0, 159, 146, 150]).unwrap(),
+        };
+        let encoded = encode_event(&event);
+        assert_eq!(encoded["type"], "gossip_message");
+        assert_eq!(encoded["source"], source.to_string());
+        assert_eq!(encoded["topic"], "chat");
+        assert_eq!(
+            decode_bytes(encoded["payload"].as_str().unwrap()).unwrap(),
+            vec![0, 159, 146, 150]
+        );
+    }
+
+    #[test]
+    fn response_carries_either_result_or_error() {
+        let ok = serde_json::to_value(Response::new(1.into(), Ok(Value::Null))).unwrap();
+        assert_eq!(ok, json!({ "jsonrpc": "2.0", "id": 1, "result": null }));
+
+        let err = Response::new(2.into(), Err(RpcError::method_not_found("nope")));
+        let err = serde_json::to_value(err).unwrap();
+        assert_eq!(err["error"]["code"], METHOD_NOT_FOUND);
+        assert!(err.get("result").is_none());
+    }
+
+    #[test]
+    fn missing_params_are_rejected() {
+        assert!(params::<KeyParams>(Value::Null).is_err());
+        assert!(params::<EventsParams>(Value::Null).is_ok());
+    }
+}
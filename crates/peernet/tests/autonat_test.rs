@@ -0,0 +1,78 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode};
+use peernet_core::{DhtMode, NetworkEvent, Reachability};
+use peernet_network::{AutonatConfig, NetworkConfig, SwarmConfig};
+use std::time::Duration;
+
+// Loopback peers only count as probe servers once global-IP filtering is off.
+fn probing(dht_mode: Option<DhtMode>) -> SwarmConfig {
+    SwarmConfig {
+        enable_mdns: false,
+        dht_mode,
+        autonat: AutonatConfig {
+            boot_delay: Duration::from_millis(500),
+            retry_interval: Duration::from_secs(1),
+            only_global_ips: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+async fn expect_reachability(node: &mut TestNode) -> (Reachability, DhtMode) {
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[{}] timeout waiting for reachability",
+            node.name
+        );
+        if let Some(NetworkEvent::ReachabilityChanged {
+            reachability,
+            dht_mode,
+        }) = node.recv_timeout(Duration::from_millis(200)).await
+        {
+            return (reachability, dht_mode);
+        }
+    }
+}
+
+async fn probe_through_server(dht_mode: Option<DhtMode>) -> (Reachability, DhtMode) {
+    let server = TestNode::spawn_with(
+        "server",
+        NetworkConfig {
+            swarm: probing(None),
+            ..Default::default()
+        },
+    )
+    .await;
+    let mut client = TestNode::spawn_with(
+        "client",
+        NetworkConfig {
+            bootstrap_peers: vec![server.loopback_addr("/tcp/")],
+            swarm: probing(dht_mode),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let result = expect_reachability(&mut client).await;
+    server.shutdown().await;
+    client.shutdown().await;
+    result
+}
+
+#[tokio::test]
+async fn reachable_node_serves_the_dht() {
+    let (reachability, dht_mode) = probe_through_server(None).await;
+    assert!(matches!(reachability, Reachability::Public(_)));
+    assert_eq!(dht_mode, DhtMode::Server);
+}
+
+#[tokio::test]
+async fn configured_dht_mode_overrides_reachability() {
+    let (reachability, dht_mode) = probe_through_server(Some(DhtMode::Client)).await;
+    assert!(matches!(reachability, Reachability::Public(_)));
+    assert_eq!(dht_mode, DhtMode::Client);
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode, wait_for_connection};
+use peernet_core::{DhtKey, DhtValue, Multiaddr, NetworkEvent};
+use peernet_network::{NetworkConfig, SwarmConfig};
+use std::time::Duration;
+
+fn without_mdns(bootstrap_peers: Vec<Multiaddr>) -> NetworkConfig {
+    NetworkConfig {
+        bootstrap_peers,
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+async fn expect_bootstrap_completed(node: &mut TestNode) {
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+
+    loop {
+        if tokio::time::Instant::now() > deadline {
+            panic!("[{}] timeout waiting for bootstrap", node.name);
+        }
+        match node.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::BootstrapCompleted) => return,
+            Some(NetworkEvent::BootstrapFailed { reason }) => {
+                panic!("[{}] bootstrap failed: {reason}", node.name);
+            }
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn connects_to_bootstrap_peer_without_mdns() {
+    let mut seed = TestNode::spawn_with("seed", without_mdns(Vec::new())).await;
+    let seed_addr = seed.loopback_addr("/tcp/");
+
+    let mut node = TestNode::spawn_with("node", without_mdns(vec![seed_addr])).await;
+
+    wait_for_connection(&mut node, &mut seed).await;
+    expect_bootstrap_completed(&mut node).await;
+
+    node.shutdown().await;
+    seed.shutdown().await;
+}
+
+#[tokio::test]
+async fn bootstrapped_nodes_share_records() {
+    let seed = TestNode::spawn_with("seed", without_mdns(Vec::new())).await;
+    let seed_addr = seed.loopback_addr("/tcp/");
+
+    let mut writer = TestNode::spawn_with("writer", without_mdns(vec![seed_addr.clone()])).await;
+    let mut reader = TestNode::spawn_with("reader", without_mdns(vec![seed_addr])).await;
+
+    expect_bootstrap_completed(&mut writer).await;
+    expect_bootstrap_completed(&mut reader).await;
+
+    let key = DhtKey::new("bootstrapped").unwrap();
+    let value = DhtValue::new(b"across the seed".to_vec()).unwrap();
+
+    writer
+        .resolve(writer.handle.put(key.clone(), value.clone()))
+        .await
+        .unwrap();
+    let found = reader.resolve(reader.handle.get(key)).await.unwrap();
+
+    assert_eq!(found, value);
+
+    writer.shutdown().await;
+    reader.shutdown().await;
+    seed.shutdown().await;
+}
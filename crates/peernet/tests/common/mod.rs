@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
-use peernet_core::{DhtKey, DhtValue, GossipPayload, NetworkEvent, PeerId};
+use peernet_core::{
+    DhtKey, DhtValue, GossipPayload, Multiaddr, NetworkCommand, NetworkEvent, PeerId, PeernetResult,
+};
 use peernet_network::{NetworkConfig, NetworkHandle};
 use std::time::Duration;
 use tokio::time::timeout;
@@ -11,34 +13,78 @@ pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
 pub struct TestNode {
     pub handle: NetworkHandle,
     pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
     pub cancel_token: CancellationToken,
     pub name: String,
 }
 
 impl TestNode {
     pub async fn spawn(name: impl Into<String>) -> Self {
+        Self::spawn_with(name, NetworkConfig::default()).await
+    }
+
+    pub async fn spawn_with(name: impl Into<String>, config: NetworkConfig) -> Self {
         let name = name.into();
         let cancel_token = CancellationToken::new();
-        let mut handle = peernet_network::spawn(NetworkConfig::default(), cancel_token.clone());
+        let mut handle = peernet_network::spawn(config, cancel_token.clone());
 
         let started = timeout(DEFAULT_TIMEOUT, handle.recv())
             .await
             .unwrap_or_else(|_| panic!("[{name}] timeout"))
             .unwrap_or_else(|| panic!("[{name}] closed"));
 
-        let peer_id = match started {
-            NetworkEvent::Started { local_peer_id, .. } => local_peer_id,
+        let (peer_id, listen_addrs) = match started {
+            NetworkEvent::Started {
+                local_peer_id,
+                listening_on,
+            } => (local_peer_id, listening_on),
             other => panic!("[{name}] expected Started, got {other:?}"),
         };
 
         Self {
             handle,
             peer_id,
+            listen_addrs,
             cancel_token,
             name,
         }
     }
 
+    // Nodes on the memory transport share nothing but the process, so many
+    // of them can run side by side without mDNS or real sockets.
+    pub async fn spawn_memory(name: impl Into<String>) -> Self {
+        Self::spawn_with(name, NetworkConfig::memory()).await
+    }
+
+    pub fn memory_addr(&self) -> Multiaddr {
+        let addr = self
+            .listen_addrs
+            .iter()
+            .find(|a| a.to_string().starts_with("/memory/"))
+            .unwrap_or_else(|| panic!("[{}] no memory address", self.name));
+        format!("{addr}/p2p/{}", self.peer_id).parse().unwrap()
+    }
+
+    pub async fn dial(&self, other: &TestNode) {
+        let addr = other.memory_addr();
+        self.handle
+            .send(NetworkCommand::Dial { addr })
+            .await
+            .unwrap_or_else(|e| panic!("[{}] dial failed: {e}", self.name));
+    }
+
+    pub fn loopback_addr(&self, transport: &str) -> Multiaddr {
+        let addr = self
+            .listen_addrs
+            .iter()
+            .find(|a| {
+                let a = a.to_string();
+                a.starts_with("/ip4/127.0.0.1/") && a.contains(transport)
+            })
+            .unwrap_or_else(|| panic!("[{}] no loopback {transport} address", self.name));
+        format!("{addr}/p2p/{}", self.peer_id).parse().unwrap()
+    }
+
     pub fn short_id(&self) -> String {
         self.peer_id.to_string()[..12].to_string()
     }
@@ -55,12 +101,21 @@ impl TestNode {
     pub async fn put(&self, key: &str, value: &str) {
         let k = DhtKey::new(key).unwrap();
         let v = DhtValue::new(value.as_bytes().to_vec()).unwrap();
-        self.handle.put(k, v).await.unwrap();
+        tokio::spawn(self.handle.put(k, v));
     }
 
     pub async fn get(&self, key: &str) {
         let k = DhtKey::new(key).unwrap();
-        self.handle.get(k).await.unwrap();
+        tokio::spawn(self.handle.get(k));
+    }
+
+    pub async fn resolve<T>(
+        &self,
+        reply: impl Future<Output = PeernetResult<T>>,
+    ) -> PeernetResult<T> {
+        timeout(DEFAULT_TIMEOUT, reply)
+            .await
+            .unwrap_or_else(|_| panic!("[{}] timeout waiting for reply", self.name))
     }
 
     pub async fn shutdown(self) {
@@ -71,7 +126,7 @@ impl TestNode {
     pub async fn put_bytes(&self, key: &str, value: &[u8]) {
         let k = DhtKey::new(key).unwrap();
         let v = DhtValue::new(value.to_vec()).unwrap();
-        self.handle.put(k, v).await.unwrap();
+        tokio::spawn(self.handle.put(k, v));
     }
 }
 
@@ -0,0 +1,137 @@
+#![cfg(unix)]
+
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
+    time::{Duration, Instant},
+};
+
+const PEERNET: &str = env!("CARGO_BIN_EXE_peernet");
+
+struct Daemon {
+    child: Child,
+    socket: PathBuf,
+}
+
+impl Daemon {
+    fn start(name: &str, args: &[&str]) -> Self {
+        let socket =
+            std::env::temp_dir().join(format!("peernet-{}-{name}.sock", std::process::id()));
+        let child = Command::new(PEERNET)
+            .arg("--no-mdns")
+            .args(args)
+            .args(["daemon", "--socket"])
+            .arg(&socket)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !socket.exists() {
+            assert!(Instant::now() < deadline, "daemon did not open its socket");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        Self { child, socket }
+    }
+
+    fn ctl(&self, args: &[&str]) -> Output {
+        ctl(&self.socket, args)
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+fn ctl(socket: &Path, args: &[&str]) -> Output {
+    Command::new(PEERNET)
+        .arg("ctl")
+        .arg("--socket")
+        .arg(socket)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn ctl_drives_daemon() {
+    let mut daemon = Daemon::start("ctl", &[]);
+
+    let mode = std::fs::metadata(&daemon.socket)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let status = daemon.ctl(&["status"]);
+    assert!(status.status.success());
+    let status: serde_json::Value = serde_json::from_slice(&status.stdout).unwrap();
+    assert!(status["peer_id"].as_str().unwrap().starts_with("12D3K"));
+    assert_eq!(status["connected_peers"], 0);
+
+    assert!(daemon.ctl(&["subscribe", "chat"]).status.success());
+    let status = daemon.ctl(&["status"]);
+    assert!(stdout(&status).contains("\"chat\""));
+
+    // With no peers the put cannot reach its quorum, but the record is still stored locally.
+    daemon.ctl(&["put", "greeting", "hello"]);
+    let get = daemon.ctl(&["get", "greeting"]);
+    assert!(get.status.success());
+    assert_eq!(stdout(&get).trim(), "hello");
+
+    let missing = daemon.ctl(&["get", "absent"]);
+    assert!(!missing.status.success());
+
+    assert!(daemon.ctl(&["shutdown"]).status.success());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while daemon.child.try_wait().unwrap().is_none() {
+        assert!(Instant::now() < deadline, "daemon did not exit");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!daemon.socket.exists());
+}
+
+#[test]
+fn peers_reports_peer_info() {
+    let first = Daemon::start("first", &[]);
+    let status: serde_json::Value = serde_json::from_slice(&first.ctl(&["status"]).stdout).unwrap();
+    let peer_id = status["peer_id"].as_str().unwrap().to_string();
+    let addr = status["listen_addrs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|a| a.as_str())
+        .find(|a| a.starts_with("/ip4/127.0.0.1/tcp/"))
+        .unwrap();
+    let bootstrap = format!("{addr}/p2p/{peer_id}");
+    let second = Daemon::start("second", &["--bootstrap", &bootstrap]);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let peers = loop {
+        let peers: serde_json::Value =
+            serde_json::from_slice(&second.ctl(&["peers"]).stdout).unwrap();
+        if !peers.as_array().unwrap().is_empty() {
+            break peers;
+        }
+        assert!(Instant::now() < deadline, "daemons did not connect");
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    let peer = &peers[0];
+    assert_eq!(peer["peer_id"], peer_id.as_str());
+    assert_eq!(peer["direction"], "dialer");
+    assert!(peer["connections"].as_u64().unwrap() >= 1);
+    assert!(peer["connected_since"].is_string());
+    assert!(peer["addresses"].is_array());
+}
@@ -4,8 +4,9 @@ use common::{
     DEFAULT_TIMEOUT, TestNode, drain_events, expect_record_found, expect_record_stored,
     wait_for_connection, wait_for_peer_count,
 };
-use peernet_core::NetworkEvent;
-use std::time::Duration;
+use peernet_core::{CommandError, DhtKey, DhtQuorum, DhtValue, NetworkEvent, PeerId, PeernetError};
+use peernet_network::{NetworkConfig, PutOptions, SwarmConfig};
+use std::{num::NonZeroUsize, time::Duration};
 
 #[tokio::test]
 async fn put_get_same_node() {
@@ -191,3 +192,260 @@ async fn binary_data() {
     node1.shutdown().await;
     node2.shutdown().await;
 }
+
+#[tokio::test]
+async fn awaited_put_get() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    drain_events(&mut node1).await;
+
+    let key = DhtKey::new("awaited").unwrap();
+    let value = DhtValue::new(b"reply".to_vec()).unwrap();
+
+    let put = node1.handle.put(key.clone(), value.clone());
+    node1.resolve(put).await.unwrap();
+
+    let get = node1.handle.get(key);
+    let retrieved = node1.resolve(get).await.unwrap();
+
+    assert_eq!(retrieved, value);
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn awaited_concurrent_gets_same_key() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    drain_events(&mut node1).await;
+
+    let key = DhtKey::new("concurrent").unwrap();
+    let value = DhtValue::new(b"same".to_vec()).unwrap();
+
+    let put = node1.handle.put(key.clone(), value.clone());
+    node1.resolve(put).await.unwrap();
+
+    let first = node1.handle.get(key.clone());
+    let second = node1.handle.get(key);
+    let (first, second) = node1
+        .resolve(async { Ok(tokio::join!(first, second)) })
+        .await
+        .unwrap();
+
+    assert_eq!(first.unwrap(), value);
+    assert_eq!(second.unwrap(), value);
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn awaited_get_missing_key() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    drain_events(&mut node1).await;
+
+    let get = node1.handle.get(DhtKey::new("absent").unwrap());
+    let result = node1.resolve(get).await;
+
+    assert!(matches!(
+        result,
+        Err(PeernetError::Command(CommandError::RecordNotFound { .. }))
+    ));
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn awaited_providers() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    drain_events(&mut node1).await;
+    drain_events(&mut node2).await;
+
+    let key = DhtKey::new("provided").unwrap();
+
+    let provide = node1.handle.start_providing(key.clone());
+    node1.resolve(provide).await.unwrap();
+
+    let lookup = node2.handle.get_providers(key);
+    let providers = node2.resolve(lookup).await.unwrap();
+
+    assert!(providers.contains(&node1.peer_id));
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+async fn expect_record_expired(node: &mut TestNode, expected_key: &str) {
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+
+    loop {
+        if tokio::time::Instant::now() > deadline {
+            panic!(
+                "[{}] timeout waiting for '{expected_key}' to expire",
+                node.name
+            );
+        }
+        match node.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::RecordExpired { key }) if key.as_str() == expected_key => return,
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn record_with_ttl_expires() {
+    let config = NetworkConfig {
+        record_expiry_interval: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let mut node1 = TestNode::spawn_with("node1", config.clone()).await;
+    let mut node2 = TestNode::spawn_with("node2", config).await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    drain_events(&mut node1).await;
+    drain_events(&mut node2).await;
+
+    let key = DhtKey::new("short-lived").unwrap();
+    let value = DhtValue::new(b"gone soon".to_vec()).unwrap();
+
+    let put = node1
+        .handle
+        .put_with_ttl(key.clone(), value, Duration::from_secs(2));
+    node1.resolve(put).await.unwrap();
+
+    expect_record_expired(&mut node1, "short-lived").await;
+    expect_record_expired(&mut node2, "short-lived").await;
+
+    let result = node1.resolve(node1.handle.get(key)).await;
+    assert!(matches!(
+        result,
+        Err(PeernetError::Command(CommandError::RecordNotFound { .. }))
+    ));
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn quorum_get_reports_agreement() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+    let mut node3 = TestNode::spawn("node3").await;
+
+    wait_for_peer_count(&mut node1, 2).await;
+    wait_for_connection(&mut node2, &mut node3).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let key = DhtKey::new("replicated-config").unwrap();
+    let value = DhtValue::new(b"v1".to_vec()).unwrap();
+    let two = DhtQuorum::N(NonZeroUsize::new(2).unwrap());
+
+    let options = PutOptions {
+        quorum: two,
+        ..Default::default()
+    };
+    let put = node1.handle.put_with(key.clone(), value.clone(), options);
+    node1.resolve(put).await.unwrap();
+
+    let get = node2.handle.get_with_quorum(key, two);
+    let lookup = node2.resolve(get).await.unwrap();
+
+    assert_eq!(lookup.value, value);
+    assert!(lookup.responses >= 2);
+    assert_eq!(lookup.agreeing, lookup.responses);
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+    node3.shutdown().await;
+}
+
+#[tokio::test]
+async fn quorum_one_get_answers_from_local_copy() {
+    // A peer that accepts connections but never answers keeps the network
+    // query open until it times out.
+    let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!(
+        "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+        silent.local_addr().unwrap().port(),
+        PeerId::random()
+    );
+    let config = NetworkConfig {
+        bootstrap_peers: vec![addr.parse().unwrap()],
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let node = TestNode::spawn_with("stalled", config).await;
+
+    let key = DhtKey::new("local-first").unwrap();
+    let value = DhtValue::new(b"here".to_vec()).unwrap();
+    tokio::spawn(node.handle.put(key.clone(), value.clone()));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let get = node.handle.get_with_quorum(key, DhtQuorum::One);
+    let lookup = tokio::time::timeout(Duration::from_secs(2), get)
+        .await
+        .expect("quorum-one get waited for the network")
+        .unwrap();
+
+    assert_eq!(lookup.value, value);
+    assert_eq!(lookup.agreeing, 1);
+    assert_eq!(lookup.responses, 1);
+
+    node.shutdown().await;
+}
+
+#[tokio::test]
+async fn quorum_not_reached_without_peers() {
+    let config = NetworkConfig {
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let node = TestNode::spawn_with("alone", config).await;
+
+    let key = DhtKey::new("lonely").unwrap();
+    let two = DhtQuorum::N(NonZeroUsize::new(2).unwrap());
+    let options = PutOptions {
+        quorum: two,
+        ..Default::default()
+    };
+
+    let put = node
+        .handle
+        .put_with(key.clone(), DhtValue::new(b"v".to_vec()).unwrap(), options);
+    assert!(node.resolve(put).await.is_err());
+
+    let get = node.handle.get_with_quorum(key, two);
+    assert!(matches!(
+        node.resolve(get).await,
+        Err(PeernetError::Command(CommandError::QuorumFailed {
+            found: 0,
+            required: 2,
+            ..
+        }))
+    ));
+
+    node.shutdown().await;
+}
@@ -2,6 +2,7 @@ mod common;
 
 use common::{DEFAULT_TIMEOUT, TestNode, wait_for_connection, wait_for_peer_count};
 use peernet_core::NetworkEvent;
+use peernet_network::{NetworkConfig, SwarmConfig};
 use std::time::Duration;
 
 #[tokio::test]
@@ -99,3 +100,112 @@ async fn late_joiner_discovers() {
     node2.shutdown().await;
     node3.shutdown().await;
 }
+
+#[tokio::test]
+async fn peers_exchange_identify_info() {
+    let config = NetworkConfig {
+        swarm: SwarmConfig {
+            agent_version: "peernet-test/1.2.3".into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node1 = TestNode::spawn("node1").await;
+    let node2 = TestNode::spawn_with("node2", config).await;
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[node1] timeout waiting for identify"
+        );
+        match node1.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerIdentified {
+                peer_id,
+                agent_version,
+                protocols,
+                listen_addrs,
+                ..
+            }) if peer_id == node2.peer_id => {
+                assert_eq!(agent_version, "peernet-test/1.2.3");
+                assert!(protocols.iter().any(|p| p == "/peernet/kad/1.0.0"));
+                assert!(!listen_addrs.is_empty());
+                break;
+            }
+            _ => continue,
+        }
+    }
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn measures_peer_latency() {
+    let config = || NetworkConfig {
+        swarm: SwarmConfig {
+            ping_interval: Duration::from_millis(200),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node1 = TestNode::spawn_with("node1", config()).await;
+    let node2 = TestNode::spawn_with("node2", config()).await;
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    let stats = loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[node1] timeout waiting for latency"
+        );
+        match node1.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerLatency { peer_id, stats })
+                if peer_id == node2.peer_id && stats.samples >= 2 =>
+            {
+                break stats;
+            }
+            _ => continue,
+        }
+    };
+    assert!(stats.min <= stats.average);
+
+    let reported = node1
+        .resolve(node1.handle.latency(node2.peer_id))
+        .await
+        .unwrap()
+        .expect("latency recorded");
+    assert!(reported.samples >= stats.samples);
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn lists_peer_connection_details() {
+    let node1 = TestNode::spawn("node1").await;
+    let node2 = TestNode::spawn("node2").await;
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    let info = loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[node1] timeout waiting for peer details"
+        );
+        let peers = node1.resolve(node1.handle.peers()).await.unwrap();
+        match peers.into_iter().find(|info| info.peer_id == node2.peer_id) {
+            Some(info)
+                if info.agent_version.is_some()
+                    && info.topics.iter().any(|t| t.as_str() == "peernet-global") =>
+            {
+                break info;
+            }
+            _ => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    };
+    assert!(info.connections >= 1);
+    assert!(!info.addresses.is_empty());
+    assert!(info.protocols.iter().any(|p| p == "/peernet/kad/1.0.0"));
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
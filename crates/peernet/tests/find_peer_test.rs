@@ -0,0 +1,88 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode, wait_for_connection};
+use peernet_core::{CommandError, DhtKey, NetworkEvent, PeerId, PeernetError};
+use std::time::Duration;
+
+// Two leaves that only know the hub, so anything they learn about each other
+// has to come from a Kademlia lookup.
+async fn star() -> (TestNode, TestNode, TestNode) {
+    let mut hub = TestNode::spawn_memory("hub").await;
+    let mut leaf1 = TestNode::spawn_memory("leaf1").await;
+    let mut leaf2 = TestNode::spawn_memory("leaf2").await;
+    leaf1.dial(&hub).await;
+    leaf2.dial(&hub).await;
+    wait_for_connection(&mut leaf1, &mut hub).await;
+    wait_for_connection(&mut leaf2, &mut hub).await;
+    (hub, leaf1, leaf2)
+}
+
+async fn expect_identified(node: &mut TestNode, peer: PeerId) {
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[{}] timeout waiting for identify",
+            node.name
+        );
+        match node.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerIdentified { peer_id, .. }) if peer_id == peer => return,
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn dials_a_peer_found_through_the_dht() {
+    let (mut hub, mut leaf1, mut leaf2) = star().await;
+    // The hub learns leaf2's listen address from identify.
+    expect_identified(&mut hub, leaf2.peer_id).await;
+
+    let addresses = leaf1
+        .resolve(leaf1.handle.find_peer(leaf2.peer_id))
+        .await
+        .unwrap();
+    assert!(addresses.contains(&leaf2.memory_addr()));
+
+    wait_for_connection(&mut leaf1, &mut leaf2).await;
+
+    hub.shutdown().await;
+    leaf1.shutdown().await;
+    leaf2.shutdown().await;
+}
+
+#[tokio::test]
+async fn unknown_peer_is_not_found() {
+    let (hub, leaf1, leaf2) = star().await;
+
+    let result = leaf1
+        .resolve(leaf1.handle.find_peer(PeerId::random()))
+        .await;
+    assert!(matches!(
+        result,
+        Err(PeernetError::Command(CommandError::PeerNotFound { .. }))
+    ));
+
+    hub.shutdown().await;
+    leaf1.shutdown().await;
+    leaf2.shutdown().await;
+}
+
+#[tokio::test]
+async fn lists_closest_peers_to_a_key() {
+    let (mut hub, leaf1, leaf2) = star().await;
+    expect_identified(&mut hub, leaf2.peer_id).await;
+
+    let key = DhtKey::new("closest").unwrap();
+    let peers = leaf1
+        .resolve(leaf1.handle.closest_peers(key))
+        .await
+        .unwrap();
+    assert!(peers.contains(&hub.peer_id));
+    assert!(peers.contains(&leaf2.peer_id));
+    assert!(!peers.contains(&leaf1.peer_id));
+
+    hub.shutdown().await;
+    leaf1.shutdown().await;
+    leaf2.shutdown().await;
+}
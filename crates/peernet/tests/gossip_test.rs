@@ -4,7 +4,8 @@ use common::{
     DEFAULT_TIMEOUT, TestNode, drain_events, expect_gossip, wait_for_connection,
     wait_for_peer_count,
 };
-use peernet_core::NetworkEvent;
+use peernet_core::{CommandError, GossipPayload, NetworkEvent, PeernetError, TopicName};
+use peernet_network::EventFilter;
 use std::time::Duration;
 
 #[tokio::test]
@@ -86,7 +87,15 @@ async fn deduplication() {
 
     let msg = "duplicate";
     node1.publish(msg).await;
-    node1.publish(msg).await;
+    let again = node1
+        .handle
+        .publish(GossipPayload::from_text(msg).unwrap())
+        .await;
+    assert!(matches!(
+        again,
+        Err(PeernetError::Command(CommandError::PublishFailed { ref reason, .. }))
+            if reason == "Duplicate"
+    ));
 
     expect_gossip(&mut node2, msg).await;
 
@@ -170,3 +179,114 @@ async fn late_joiner_receives_new() {
     node2.shutdown().await;
     node3.shutdown().await;
 }
+
+#[tokio::test]
+async fn filtered_subscription_receives_topic() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    let topic = TopicName::new("peernet-global").unwrap();
+    let mut messages = node2
+        .handle
+        .subscribe_events(EventFilter::Topic(topic.clone()));
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let msg = "filtered";
+    node1.publish(msg).await;
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        let event = tokio::time::timeout_at(deadline, messages.recv())
+            .await
+            .expect("timeout waiting for filtered message")
+            .expect("subscription closed");
+
+        match event {
+            NetworkEvent::GossipMessage {
+                topic: t, payload, ..
+            } => {
+                assert_eq!(t, topic);
+                if payload.as_bytes() == msg.as_bytes() {
+                    break;
+                }
+            }
+            other => panic!("expected GossipMessage, got {other:?}"),
+        }
+    }
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn publishes_to_joined_topic() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    let topic = TopicName::new("room-42").unwrap();
+    node1
+        .resolve(node1.handle.subscribe(topic.clone()))
+        .await
+        .unwrap();
+    node2
+        .resolve(node2.handle.subscribe(topic.clone()))
+        .await
+        .unwrap();
+
+    let topics = node2.resolve(node2.handle.subscriptions()).await.unwrap();
+    assert!(topics.contains(&topic));
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let mut messages = node2
+        .handle
+        .subscribe_events(EventFilter::Topic(topic.clone()));
+
+    let payload = GossipPayload::from_text("in the room").unwrap();
+    node1
+        .resolve(node1.handle.publish_to(topic.clone(), payload.clone()))
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(DEFAULT_TIMEOUT, messages.recv())
+        .await
+        .expect("timeout waiting for topic message")
+        .expect("subscription closed");
+    match event {
+        NetworkEvent::GossipMessage {
+            topic: t,
+            payload: p,
+            ..
+        } => {
+            assert_eq!(t, topic);
+            assert_eq!(p, payload);
+        }
+        other => panic!("expected GossipMessage, got {other:?}"),
+    }
+
+    node2
+        .resolve(node2.handle.unsubscribe(topic.clone()))
+        .await
+        .unwrap();
+    let topics = node2.resolve(node2.handle.subscriptions()).await.unwrap();
+    assert!(!topics.contains(&topic));
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn publish_to_unjoined_topic_fails() {
+    let node1 = TestNode::spawn("node1").await;
+
+    let topic = TopicName::new("never-joined").unwrap();
+    let payload = GossipPayload::from_text("nobody home").unwrap();
+    let result = node1.resolve(node1.handle.publish_to(topic, payload)).await;
+
+    assert!(result.is_err());
+
+    node1.shutdown().await;
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{
+    DEFAULT_TIMEOUT, TestNode, expect_gossip, expect_record_found, expect_record_stored,
+    wait_for_connection,
+};
+use peernet_core::{NetworkEvent, PeerId};
+use std::{collections::HashSet, time::Duration};
+
+async fn wait_for_subscribers(node: &mut TestNode, peers: &[PeerId]) {
+    let mut pending: HashSet<PeerId> = peers.iter().copied().collect();
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    while !pending.is_empty() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[{}] timeout waiting for {} subscribers",
+            node.name,
+            pending.len()
+        );
+        if let Some(NetworkEvent::PeerSubscribed { peer_id, .. }) =
+            node.recv_timeout(Duration::from_millis(200)).await
+        {
+            pending.remove(&peer_id);
+        }
+    }
+}
+
+#[tokio::test]
+async fn memory_nodes_connect_by_dialing() {
+    let mut node1 = TestNode::spawn_memory("node1").await;
+    let mut node2 = TestNode::spawn_memory("node2").await;
+    assert!(
+        node1
+            .listen_addrs
+            .iter()
+            .all(|a| a.to_string().starts_with("/memory/"))
+    );
+
+    node2.dial(&node1).await;
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn star_of_memory_nodes_shares_gossip_and_records() {
+    const LEAVES: usize = 8;
+
+    let mut hub = TestNode::spawn_memory("hub").await;
+    let mut leaves = Vec::new();
+    for i in 0..LEAVES {
+        let leaf = TestNode::spawn_memory(format!("leaf{i}")).await;
+        leaf.dial(&hub).await;
+        leaves.push(leaf);
+    }
+    // A subscription can only arrive over an open connection, so this also
+    // waits for every leaf to connect.
+    let leaf_ids: Vec<PeerId> = leaves.iter().map(|l| l.peer_id).collect();
+    wait_for_subscribers(&mut hub, &leaf_ids).await;
+    let hub_id = hub.peer_id;
+    wait_for_subscribers(&mut leaves[0], &[hub_id]).await;
+
+    leaves[0].publish("hello, star").await;
+    for leaf in &mut leaves[1..] {
+        expect_gossip(leaf, "hello, star").await;
+    }
+
+    leaves[0].put("star", "shine").await;
+    expect_record_stored(&mut leaves[0], "star").await;
+    let last = leaves.last_mut().unwrap();
+    last.get("star").await;
+    assert_eq!(expect_record_found(last, "star").await, b"shine");
+
+    hub.shutdown().await;
+    for leaf in leaves {
+        leaf.shutdown().await;
+    }
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::TestNode;
+use peernet_network::NetworkConfig;
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+fn free_local_addr() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn http_get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn serves_prometheus_metrics() {
+    let metrics_addr = free_local_addr();
+    let config = NetworkConfig {
+        metrics_addr: Some(metrics_addr),
+        ..Default::default()
+    };
+    let node = TestNode::spawn_with("metrics", config).await;
+
+    node.resolve(node.handle.subscriptions()).await.unwrap();
+
+    let response = http_get(metrics_addr, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("peernet_commands_total{command=\"list_subscriptions\"} 1"));
+    assert!(response.contains("peernet_pending_queries"));
+    assert!(response.contains("libp2p_swarm"));
+    assert!(response.contains("libp2p_kad"));
+    assert!(response.contains("gossipsub_topic_subscription_status"));
+
+    let missing = http_get(metrics_addr, "/").await;
+    assert!(missing.starts_with("HTTP/1.1 404"));
+
+    node.shutdown().await;
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{TestNode, expect_gossip};
+use peernet_core::{DhtKey, TopicName};
+use peernet_network::{NetworkConfig, SwarmConfig};
+use std::process::{Command, Output};
+
+const PEERNET: &str = env!("CARGO_BIN_EXE_peernet");
+
+async fn seed() -> TestNode {
+    let config = NetworkConfig {
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    TestNode::spawn_with("seed", config).await
+}
+
+// The child blocks, so run it off the runtime thread that drives the seed node.
+async fn peernet(seed: &TestNode, args: &[&str]) -> Output {
+    let mut command = Command::new(PEERNET);
+    command
+        .args(["--no-mdns", "--bootstrap"])
+        .arg(seed.loopback_addr("/tcp/").to_string())
+        .args(args);
+    tokio::task::spawn_blocking(move || command.output().unwrap())
+        .await
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[tokio::test]
+async fn put_then_get_through_a_peer() {
+    let seed = seed().await;
+
+    let put = peernet(&seed, &["put", "oneshot-key", "hello world"]).await;
+    assert!(put.status.success(), "put failed: {}", stdout(&put));
+    assert!(stdout(&put).contains("[stored] oneshot-key"));
+
+    let get = peernet(&seed, &["get", "oneshot-key"]).await;
+    assert!(get.status.success(), "get failed: {}", stdout(&get));
+    assert_eq!(stdout(&get), "hello world\n");
+
+    let local = seed
+        .resolve(seed.handle.get(DhtKey::new("oneshot-key").unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(local.as_bytes(), b"hello world");
+
+    seed.shutdown().await;
+}
+
+#[tokio::test]
+async fn missing_record_exits_with_not_found() {
+    let seed = seed().await;
+
+    let get = peernet(&seed, &["--output", "json", "get", "oneshot-missing"]).await;
+    assert_eq!(get.status.code(), Some(3));
+    let error: serde_json::Value = serde_json::from_str(stdout(&get).trim()).unwrap();
+    assert_eq!(error["type"], "error");
+
+    seed.shutdown().await;
+}
+
+#[tokio::test]
+async fn publish_reaches_subscribed_peer() {
+    let mut seed = seed().await;
+    let topic = TopicName::new("oneshot").unwrap();
+    seed.resolve(seed.handle.subscribe(topic)).await.unwrap();
+
+    let publish = peernet(&seed, &["publish", "oneshot", "ping from cli"]).await;
+    assert!(
+        publish.status.success(),
+        "publish failed: {}",
+        stdout(&publish)
+    );
+
+    expect_gossip(&mut seed, "ping from cli").await;
+    seed.shutdown().await;
+}
+
+#[test]
+fn times_out_without_peers() {
+    let output = Command::new(PEERNET)
+        .args(["--no-mdns", "get", "k", "--timeout", "1"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn rejects_invalid_key_before_starting() {
+    let output = Command::new(PEERNET)
+        .args(["--no-mdns", "get", ""])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode, wait_for_connection};
+use peernet_core::{NetworkCommand, NetworkEvent, PeerId};
+use peernet_network::{NetworkConfig, PeerListsConfig, SwarmConfig};
+use std::time::Duration;
+
+async fn expect_no_connection(node: &mut TestNode, other: PeerId) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while tokio::time::Instant::now() < deadline {
+        if let Some(NetworkEvent::PeerConnected { peer_id }) =
+            node.recv_timeout(Duration::from_millis(200)).await
+        {
+            assert_ne!(peer_id, other, "[{}] connected to a denied peer", node.name);
+        }
+    }
+}
+
+#[tokio::test]
+async fn banned_peer_is_disconnected_and_kept_out() {
+    let mut node1 = TestNode::spawn("node1").await;
+    let mut node2 = TestNode::spawn("node2").await;
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    node1
+        .resolve(node1.handle.block_peer(node2.peer_id))
+        .await
+        .unwrap();
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[node1] timeout waiting for disconnect"
+        );
+        match node1.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerDisconnected { peer_id }) if peer_id == node2.peer_id => break,
+            _ => continue,
+        }
+    }
+    let banned = node1.resolve(node1.handle.blocked_peers()).await.unwrap();
+    assert_eq!(banned, vec![node2.peer_id]);
+
+    expect_no_connection(&mut node1, node2.peer_id).await;
+
+    node1
+        .resolve(node1.handle.unblock_peer(node2.peer_id))
+        .await
+        .unwrap();
+    let banned = node1.resolve(node1.handle.blocked_peers()).await.unwrap();
+    assert!(banned.is_empty());
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn allowlist_only_node_rejects_strangers() {
+    let mut node1 = TestNode::spawn_with(
+        "node1",
+        NetworkConfig {
+            swarm: SwarmConfig {
+                peer_lists: PeerListsConfig {
+                    data_dir: None,
+                    only_allowed: true,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+    // node2 may briefly see the connection before node1 drops it, so only the
+    // allowlisting side is checked.
+    let node2 = TestNode::spawn_with(
+        "node2",
+        NetworkConfig {
+            bootstrap_peers: vec![node1.loopback_addr("/tcp/")],
+            ..Default::default()
+        },
+    )
+    .await;
+
+    expect_no_connection(&mut node1, node2.peer_id).await;
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn disallowed_peer_is_disconnected() {
+    let mut node1 = TestNode::spawn_with(
+        "node1",
+        NetworkConfig {
+            swarm: SwarmConfig {
+                enable_mdns: false,
+                peer_lists: PeerListsConfig {
+                    data_dir: None,
+                    only_allowed: true,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+    let mut node2 = TestNode::spawn_with(
+        "node2",
+        NetworkConfig {
+            swarm: SwarmConfig {
+                enable_mdns: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    node1
+        .resolve(node1.handle.allow_peer(node2.peer_id))
+        .await
+        .unwrap();
+    node2
+        .handle
+        .send(NetworkCommand::Dial {
+            addr: node1.loopback_addr("/tcp/"),
+        })
+        .await
+        .unwrap();
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    node1
+        .resolve(node1.handle.disallow_peer(node2.peer_id))
+        .await
+        .unwrap();
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[node1] timeout waiting for disconnect"
+        );
+        match node1.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerDisconnected { peer_id }) if peer_id == node2.peer_id => break,
+            _ => continue,
+        }
+    }
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
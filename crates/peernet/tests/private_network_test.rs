@@ -0,0 +1,84 @@
+mod common;
+
+use common::{TestNode, wait_for_connection};
+use peernet_core::{NetworkEvent, PeerId};
+use peernet_network::{NetworkConfig, PreSharedKey, SwarmConfig, TransportConfig};
+use std::time::Duration;
+
+fn private(psk: Option<PreSharedKey>) -> NetworkConfig {
+    NetworkConfig {
+        transport: TransportConfig {
+            quic: false,
+            ..Default::default()
+        },
+        swarm: SwarmConfig {
+            psk,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+async fn expect_no_connection(node: &mut TestNode, other: PeerId) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while tokio::time::Instant::now() < deadline {
+        if let Some(NetworkEvent::PeerConnected { peer_id }) =
+            node.recv_timeout(Duration::from_millis(200)).await
+        {
+            assert_ne!(peer_id, other, "[{}] connected without the key", node.name);
+        }
+    }
+}
+
+#[tokio::test]
+async fn nodes_sharing_a_key_connect_over_tcp() {
+    let psk = PreSharedKey::new([1; 32]);
+    let mut node1 = TestNode::spawn_with("node1", private(Some(psk))).await;
+    let mut node2 = TestNode::spawn_with("node2", private(Some(psk))).await;
+
+    assert!(
+        node1
+            .listen_addrs
+            .iter()
+            .all(|a| a.to_string().contains("/tcp/"))
+    );
+
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn nodes_with_different_keys_never_connect() {
+    let mut node1 = TestNode::spawn_with("node1", private(Some(PreSharedKey::new([2; 32])))).await;
+    let mut config = private(Some(PreSharedKey::new([3; 32])));
+    config.bootstrap_peers = vec![node1.loopback_addr("/tcp/")];
+    let mut node2 = TestNode::spawn_with("node2", config).await;
+
+    let (peer1, peer2) = (node1.peer_id, node2.peer_id);
+    tokio::join!(
+        expect_no_connection(&mut node1, peer2),
+        expect_no_connection(&mut node2, peer1)
+    );
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn public_node_cannot_join_private_network() {
+    let mut node1 = TestNode::spawn_with("node1", private(Some(PreSharedKey::new([4; 32])))).await;
+    let mut config = private(None);
+    config.bootstrap_peers = vec![node1.loopback_addr("/tcp/")];
+    let mut node2 = TestNode::spawn_with("node2", config).await;
+
+    let (peer1, peer2) = (node1.peer_id, node2.peer_id);
+    tokio::join!(
+        expect_no_connection(&mut node1, peer2),
+        expect_no_connection(&mut node2, peer1)
+    );
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::TestNode;
+use peernet_core::{DhtKey, DhtValue, NetworkEvent, PeerId};
+use peernet_network::{NetworkConfig, RecordStoreBackend, RecordStoreConfig, SwarmConfig};
+use std::{path::Path, time::Duration};
+
+fn persistent(data_dir: &Path) -> NetworkConfig {
+    let keypair = peernet_network::load_or_generate_identity(&data_dir.join("node.key")).unwrap();
+    NetworkConfig {
+        swarm: SwarmConfig {
+            keypair: Some(keypair),
+            enable_mdns: false,
+            record_store: RecordStoreConfig {
+                backend: RecordStoreBackend::Disk {
+                    data_dir: data_dir.to_path_buf(),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+async fn stop(mut node: TestNode) {
+    node.cancel_token.cancel();
+    while let Some(event) = node.recv_timeout(Duration::from_secs(5)).await {
+        if matches!(event, NetworkEvent::ShutdownComplete) {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn records_survive_restart() {
+    let data_dir = std::env::temp_dir().join(format!("peernet-records-{}", PeerId::random()));
+    let key = DhtKey::new("persisted").unwrap();
+    let value = DhtValue::new(b"still here".to_vec()).unwrap();
+
+    let node = TestNode::spawn_with("before", persistent(&data_dir)).await;
+    let _ = node
+        .resolve(node.handle.put(key.clone(), value.clone()))
+        .await;
+    let _ = node.resolve(node.handle.start_providing(key.clone())).await;
+    stop(node).await;
+
+    let node = TestNode::spawn_with("after", persistent(&data_dir)).await;
+    let found = node.resolve(node.handle.get(key.clone())).await.unwrap();
+    assert_eq!(found.as_bytes(), value.as_bytes());
+
+    let providers = node.resolve(node.handle.get_providers(key)).await.unwrap();
+    assert_eq!(providers, vec![node.peer_id]);
+
+    stop(node).await;
+    let _ = std::fs::remove_dir_all(data_dir);
+}
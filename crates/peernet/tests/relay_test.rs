@@ -0,0 +1,128 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode, expect_gossip};
+use peernet_core::{NetworkCommand, NetworkEvent, PeerId};
+use peernet_network::{NetworkConfig, RelayServerConfig, SwarmConfig, TransportConfig};
+use std::time::Duration;
+
+fn without_mdns() -> SwarmConfig {
+    SwarmConfig {
+        enable_mdns: false,
+        ..Default::default()
+    }
+}
+
+async fn expect_relayed(node: &mut TestNode, peer: PeerId, relay: PeerId) {
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[{}] timeout waiting for relayed connection",
+            node.name
+        );
+        match node.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::RelayedConnection {
+                peer_id,
+                relay_peer_id,
+            }) if peer_id == peer => {
+                assert_eq!(relay_peer_id, relay);
+                return;
+            }
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn nodes_connect_through_a_relay() {
+    let relay = TestNode::spawn_with(
+        "relay",
+        NetworkConfig {
+            swarm: SwarmConfig {
+                relay_server: RelayServerConfig {
+                    enabled: true,
+                    ..Default::default()
+                },
+                ..without_mdns()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+    let relay_addr = relay.loopback_addr("/tcp/");
+
+    // The listener has no direct addresses, so it can only be reached through
+    // its reservation on the relay.
+    let mut listener = TestNode::spawn_with(
+        "listener",
+        NetworkConfig {
+            transport: TransportConfig {
+                tcp: false,
+                quic: false,
+                ..Default::default()
+            },
+            relays: vec![relay_addr.clone()],
+            swarm: without_mdns(),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(listener.listen_addrs.is_empty());
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[listener] timeout waiting for reservation"
+        );
+        match listener.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::RelayReservationAccepted { relay_peer_id, .. })
+                if relay_peer_id == relay.peer_id =>
+            {
+                break;
+            }
+            _ => continue,
+        }
+    }
+
+    let mut dialer = TestNode::spawn_with(
+        "dialer",
+        NetworkConfig {
+            swarm: without_mdns(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let circuit = format!("{relay_addr}/p2p-circuit/p2p/{}", listener.peer_id)
+        .parse()
+        .unwrap();
+    dialer
+        .handle
+        .send(NetworkCommand::Dial { addr: circuit })
+        .await
+        .unwrap();
+
+    let (listener_id, dialer_id) = (listener.peer_id, dialer.peer_id);
+    tokio::join!(
+        expect_relayed(&mut dialer, listener_id, relay.peer_id),
+        expect_relayed(&mut listener, dialer_id, relay.peer_id)
+    );
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[dialer] timeout waiting for the listener to subscribe"
+        );
+        match dialer.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::PeerSubscribed { peer_id, .. }) if peer_id == listener_id => break,
+            _ => continue,
+        }
+    }
+    dialer.publish("via relay").await;
+    expect_gossip(&mut listener, "via relay").await;
+
+    relay.shutdown().await;
+    listener.shutdown().await;
+    dialer.shutdown().await;
+}
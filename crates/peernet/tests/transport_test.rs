@@ -0,0 +1,187 @@
+mod common;
+
+use common::{DEFAULT_TIMEOUT, TestNode, wait_for_connection};
+use peernet_core::{ConnectionDirection, Multiaddr, NetworkEvent};
+use peernet_network::{ConnectionLimitsConfig, NetworkConfig, SwarmConfig, TransportConfig};
+use std::{collections::HashSet, time::Duration};
+
+fn quic_only() -> NetworkConfig {
+    NetworkConfig {
+        transport: TransportConfig {
+            tcp: false,
+            quic: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn reports_tcp_and_quic_addrs() {
+    let node = TestNode::spawn("node").await;
+
+    let addrs: Vec<String> = node.listen_addrs.iter().map(|a| a.to_string()).collect();
+    assert!(addrs.iter().any(|a| a.contains("/tcp/")));
+    assert!(
+        addrs
+            .iter()
+            .any(|a| a.contains("/udp/") && a.ends_with("/quic-v1"))
+    );
+
+    node.shutdown().await;
+}
+
+// Every interface address must be in Started, for both transports, rather
+// than trickling in later as Listening events.
+#[tokio::test]
+async fn started_lists_every_interface_on_every_transport() {
+    let config = NetworkConfig {
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node = TestNode::spawn_with("node", config).await;
+
+    let ip_of = |addr: &Multiaddr| addr.iter().next().map(|p| p.to_string());
+    let ips: HashSet<_> = node.listen_addrs.iter().filter_map(ip_of).collect();
+    assert!(!ips.is_empty());
+    for ip in &ips {
+        let on = |transport: &str| {
+            node.listen_addrs
+                .iter()
+                .any(|a| ip_of(a).as_ref() == Some(ip) && a.to_string().contains(transport))
+        };
+        assert!(on("/tcp/"), "no tcp address on {ip}");
+        assert!(on("/quic-v1"), "no quic address on {ip}");
+    }
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+    while tokio::time::Instant::now() < deadline {
+        if let Some(NetworkEvent::Listening { address }) =
+            node.recv_timeout(Duration::from_millis(100)).await
+        {
+            panic!("{address} was missing from Started");
+        }
+    }
+
+    node.shutdown().await;
+}
+
+#[tokio::test]
+async fn quic_only_nodes_connect() {
+    let mut node1 = TestNode::spawn_with("node1", quic_only()).await;
+    let mut node2 = TestNode::spawn_with("node2", quic_only()).await;
+
+    assert!(
+        node2
+            .listen_addrs
+            .iter()
+            .all(|a| a.to_string().ends_with("/quic-v1"))
+    );
+
+    wait_for_connection(&mut node1, &mut node2).await;
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn websocket_only_nodes_connect() {
+    let websocket_only = || NetworkConfig {
+        transport: TransportConfig {
+            tcp: false,
+            quic: false,
+            websocket: true,
+            ..Default::default()
+        },
+        swarm: SwarmConfig {
+            enable_mdns: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut node1 = TestNode::spawn_with("node1", websocket_only()).await;
+    assert!(
+        node1
+            .listen_addrs
+            .iter()
+            .all(|a| a.to_string().ends_with("/ws"))
+    );
+
+    let mut config = websocket_only();
+    config.bootstrap_peers = vec![node1.loopback_addr("/ws")];
+    let mut node2 = TestNode::spawn_with("node2", config).await;
+
+    wait_for_connection(&mut node1, &mut node2).await;
+    let peers = node1.resolve(node1.handle.peers()).await.unwrap();
+    let peer = peers.iter().find(|p| p.peer_id == node2.peer_id).unwrap();
+    assert!(
+        peer.addresses
+            .iter()
+            .all(|a| a.to_string().ends_with("/ws"))
+    );
+
+    node1.shutdown().await;
+    node2.shutdown().await;
+}
+
+#[tokio::test]
+async fn denies_connections_over_the_limit() {
+    let mut seed = TestNode::spawn_with(
+        "seed",
+        NetworkConfig {
+            swarm: SwarmConfig {
+                enable_mdns: false,
+                connection_limits: ConnectionLimitsConfig {
+                    max_established_incoming: Some(0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+    let node = TestNode::spawn_with(
+        "node",
+        NetworkConfig {
+            bootstrap_peers: vec![seed.loopback_addr("/tcp/")],
+            swarm: SwarmConfig {
+                enable_mdns: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "[seed] timeout waiting for limit rejection"
+        );
+        match seed.recv_timeout(Duration::from_millis(200)).await {
+            Some(NetworkEvent::ConnectionLimitReached {
+                peer_id,
+                direction,
+                limit,
+                ..
+            }) if peer_id == Some(node.peer_id) => {
+                assert_eq!(direction, ConnectionDirection::Listener);
+                assert_eq!(limit, 0);
+                break;
+            }
+            Some(NetworkEvent::PeerConnected { peer_id }) if peer_id == node.peer_id => {
+                panic!("[seed] accepted a connection over the limit");
+            }
+            _ => continue,
+        }
+    }
+    assert!(seed.resolve(seed.handle.peers()).await.unwrap().is_empty());
+
+    node.shutdown().await;
+    seed.shutdown().await;
+}